@@ -0,0 +1,5 @@
+fn main() {
+  // The built-in level packs are compiled into the program, so rebuild whenever they change
+  println!("cargo:rerun-if-changed=packs/default-levels");
+  println!("cargo:rerun-if-changed=packs/extreme-levels");
+}
@@ -10,3 +10,13 @@ function generateTestCase()
 
   return input, output
 end
+
+function solve(input)
+  checkABCString(input, 3)
+  local a, b, c = countLetter(input, "a"), countLetter(input, "b"), countLetter(input, "c")
+  if a == 0 or b == 0 or c == 0 then
+    error("input must contain every letter at least once", 0)
+  end
+
+  return ("a"):rep(a) .. ("b"):rep(b) .. ("c"):rep(c)
+end
@@ -2,6 +2,11 @@ require("functions")
 
 function generateTestCase()
   local input = randomABCString(3, 10);
+  return input, solve(input)
+end
+
+function solve(input)
+  checkABCString(input, 3)
   local output = input:gsub("c", "A"):gsub("b", "c"):gsub("a", "b"):gsub("A", "a")
-  return input, output
+  return output
 end
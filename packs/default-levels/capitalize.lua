@@ -2,5 +2,10 @@ require("functions")
 
 function generateTestCase()
   local str = randomABCString(1, 7);
-  return str, string.upper(str)
+  return str, solve(str)
+end
+
+function solve(input)
+  checkABCString(input, 1)
+  return string.upper(input)
 end
@@ -2,7 +2,12 @@ require("functions")
 
 function generateTestCase()
   local input = randomABCString(1, 5);
+  return input, solve(input)
+end
+
+function solve(input)
+  checkABCString(input, 1)
   local output = input:gsub("a", "aa"):gsub("b", "bb"):gsub("c", "cc")
 
-  return input, output
+  return output
 end
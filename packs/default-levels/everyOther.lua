@@ -2,6 +2,11 @@ require("functions")
 
 function generateTestCase()
   local input = randomABCString(2, 13);
+  return input, solve(input)
+end
+
+function solve(input)
+  checkABCString(input, 2)
 
   local output = ""
   for i = 1, #input do
@@ -10,5 +15,5 @@ function generateTestCase()
     end
   end
 
-  return input, output
+  return output
 end
@@ -14,6 +14,15 @@ function generateTestCase()
   end
 
   local input = shuffle(("a"):rep(a) .. ("b"):rep(b) .. ("c"):rep(c))
+  return input, solve(input)
+end
+
+function solve(input)
+  checkABCString(input, 3)
+  local a, b, c = countLetter(input, "a"), countLetter(input, "b"), countLetter(input, "c")
+  if a == 0 or b == 0 or c == 0 or a == b or a == c or b == c then
+    error("input must contain every letter a different number of times", 0)
+  end
 
   local output
   local max = math.max(a, b, c)
@@ -21,5 +30,5 @@ function generateTestCase()
   elseif max == b then output = "b"
   else output = "c" end
 
-  return input, output
+  return output
 end
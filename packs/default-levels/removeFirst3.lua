@@ -2,7 +2,10 @@ require("functions")
 
 function generateTestCase()
   local input = randomABCString(3, 11);
-  local output = input:sub(4)
+  return input, solve(input)
+end
 
-  return input, output
+function solve(input)
+  checkABCString(input, 3)
+  return input:sub(4)
 end
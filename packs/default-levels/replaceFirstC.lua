@@ -2,7 +2,12 @@ require("functions")
 
 function generateTestCase()
   local input = randomABCString(1, 10);
+  return input, solve(input)
+end
+
+function solve(input)
+  checkABCString(input, 1)
   local output = input:gsub("b", "a"):gsub("c", "b", 1)
 
-  return input, output
+  return output
 end
@@ -2,7 +2,12 @@ require("functions")
 
 function generateTestCase()
   local input = randomABCString(1, 12);
+  return input, solve(input)
+end
+
+function solve(input)
+  checkABCString(input, 1)
   local output = input:gsub("a+", "a"):gsub("b+", "b"):gsub("c+", "c")
 
-  return input, output
+  return output
 end
@@ -2,6 +2,11 @@ require("functions")
 
 function generateTestCase()
   local input = randomABCString(3, 12);
+  return input, solve(input)
+end
+
+function solve(input)
+  checkABCString(input, 3)
   local output = input:gsub("^a+", ""):gsub("a+$", "")
-  return input, output
+  return output
 end
@@ -5,7 +5,14 @@ function generateTestCase()
   local b = math.random(1, 7)
 
   local input = ("1"):rep(a) .. "+" .. ("1"):rep(b)
-  local output = ("1"):rep(a + b)
+  return input, solve(input)
+end
+
+function solve(input)
+  local a, b = input:match("^(1+)%+(1+)$")
+  if not a then
+    error("input must be two unary numbers separated by '+'", 0)
+  end
 
-  return input, output
+  return ("1"):rep(#a + #b)
 end
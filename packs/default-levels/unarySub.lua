@@ -11,7 +11,14 @@ function generateTestCase()
   end
 
   local input = ("1"):rep(a) .. "-" .. ("1"):rep(b)
-  local output = ("1"):rep(a - b)
+  return input, solve(input)
+end
+
+function solve(input)
+  local a, b = input:match("^(1+)%-(1+)$")
+  if not a or #a <= #b then
+    error("input must be two unary numbers separated by '-', where the first is larger", 0)
+  end
 
-  return input, output
+  return ("1"):rep(#a - #b)
 end
@@ -5,7 +5,14 @@ function generateTestCase()
   local b = math.random(1, 7)
 
   local input = toBinary(a) .. "+" .. toBinary(b)
-  local output = toBinary(a + b)
+  return input, solve(input)
+end
+
+function solve(input)
+  local a, b = input:match("^(1[01]*)%+(1[01]*)$")
+  if not a then
+    error("input must be two binary numbers separated by '+'", 0)
+  end
 
-  return input, output
+  return toBinary(tonumber(a, 2) + tonumber(b, 2))
 end
@@ -62,3 +62,14 @@ function randomABC()
   local ALL_LETTERS = { "a", "b", "c" }
   return ALL_LETTERS[math.random(#ALL_LETTERS)]
 end
+
+-- Raise an error unless the string only contains letters a, b, and c
+--  and is at least the minimum length
+function checkABCString(str, min)
+  if not str:match("^[abc]*$") then
+    error("input can only contain the letters a, b, and c", 0)
+  end
+  if #str < min then
+    error("input must have at least " .. min .. " letters", 0)
+  end
+end
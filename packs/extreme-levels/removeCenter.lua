@@ -8,8 +8,15 @@ function generateTestCase()
     input = input .. randomABC()
   end
 
-  local center = math.ceil(#input / 2)
-  local output = input:sub(1, center - 1) .. input:sub(center + 1, #input)
+  return input, solve(input)
+end
+
+function solve(input)
+  checkABCString(input, 1)
+  if (#input % 2) == 0 then
+    error("input must have an odd length", 0)
+  end
 
-  return input, output
+  local center = math.ceil(#input / 2)
+  return input:sub(1, center - 1) .. input:sub(center + 1, #input)
 end
@@ -2,7 +2,10 @@ require("functions")
 
 function generateTestCase()
   local input = randomABCString(1, 7);
-  local output = input:reverse()
+  return input, solve(input)
+end
 
-  return input, output
+function solve(input)
+  checkABCString(input, 1)
+  return input:reverse()
 end
@@ -2,6 +2,11 @@ require("functions")
 
 function generateTestCase()
   local input = randomABCString(1, 10);
+  return input, solve(input)
+end
+
+function solve(input)
+  checkABCString(input, 1)
 
   local first = input:sub(1, 1)
   local last = input:sub(#input, #input)
@@ -13,6 +18,5 @@ function generateTestCase()
     output = first
   end
 
-
-  return input, output
+  return output
 end
@@ -4,7 +4,13 @@ function generateTestCase()
   local a = math.random(1, 16)
 
   local input = ("1"):rep(a)
-  local output = toBinary(a)
+  return input, solve(input)
+end
+
+function solve(input)
+  if not input:match("^1+$") then
+    error("input must be a unary number", 0)
+  end
 
-  return input, output
+  return toBinary(#input)
 end
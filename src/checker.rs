@@ -0,0 +1,62 @@
+use std::error::Error;
+use std::sync::Mutex;
+
+use crate::pack_source::PackSource;
+use crate::program::Outcome;
+use crate::script::LevelScript;
+use crate::trace::Verdict;
+
+///
+/// Decides if the output from the code is correct
+///   Uses the level's `check(input, output)` function if it has one,
+///   otherwise the output must exactly match the expected output.
+///
+#[derive(Default)]
+pub struct Checker {
+  script: Option<Mutex<LevelScript>>,
+}
+
+impl Checker {
+  /// Checker that only accepts the expected output
+  pub fn exact() -> Self {
+    Self::default()
+  }
+
+  ///
+  /// Load the checker for a level
+  ///   Falls back to an exact match if the script does not define `check(input, output)`
+  ///
+  pub fn load(lua_file: &str, pack_source: &PackSource) -> Result<Self, Box<dyn Error>> {
+    let script = LevelScript::load(lua_file, pack_source, 0)?;
+    if !script.has_check() {
+      return Ok(Self::exact());
+    }
+
+    Ok(Self {
+      script: Some(Mutex::new(script)),
+    })
+  }
+
+  /// See if the level decides which outputs are correct instead of using an exact match
+  pub fn has_check(&self) -> bool {
+    self.script.is_some()
+  }
+
+  ///
+  /// Get the verdict for the outcome of running the code on the input
+  ///   Also returns the reason from the level if it rejected the output
+  ///
+  pub fn verdict(&self, input: &str, outcome: &Outcome, expected: &str) -> (Verdict, Option<String>) {
+    let (script, output) = match (&self.script, outcome) {
+      (Some(script), Outcome::Finished(output)) => (script, output),
+      _ => return (Verdict::from_outcome(outcome, expected), None),
+    };
+
+    let script = script.lock().unwrap_or_else(|e| e.into_inner());
+    match script.check(input, output) {
+      Ok((true, _)) => (Verdict::Passed, None),
+      Ok((false, reason)) => (Verdict::Failed, reason),
+      Err(e) => (Verdict::Failed, Some(format!("check(input, output) failed: {e}"))),
+    }
+  }
+}
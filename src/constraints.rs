@@ -0,0 +1,151 @@
+use serde::Deserialize;
+
+use crate::program::{Anchor, Program, Rule, Target};
+
+///
+/// Limits on the code that the player can write for a level
+///   The code is checked against the constraints before running any test cases
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Constraints {
+  /// Maximum number of rules in the code
+  max_rules: Option<usize>,
+  /// Maximum number of characters in the left and right sides of a single rule
+  max_rule_length: Option<usize>,
+  /// Only these characters can be used on the left side of a rule
+  left_alphabet: Option<String>,
+  /// Only these characters can be used on the right side of a rule
+  right_alphabet: Option<String>,
+  /// These characters cannot be used anywhere in a rule
+  #[serde(default)]
+  banned_characters: String,
+  /// Types of rules that cannot be used
+  #[serde(default)]
+  forbidden_rules: Vec<RuleKind>,
+}
+
+/// Type of rule that a level can forbid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RuleKind {
+  /// Rules with a limit, `:=` or `:N=`
+  Once,
+  /// `(start)` on the left side
+  StartAnchor,
+  /// `(end)` on the left side
+  EndAnchor,
+  /// `(start)` on the right side
+  MoveToStart,
+  /// `(end)` on the right side
+  MoveToEnd,
+  /// `(return)` on the right side
+  Return,
+}
+
+impl RuleKind {
+  /// Description of the rule type to show to the player
+  pub fn description(&self) -> &'static str {
+    match self {
+      RuleKind::Once => "`:=` or `:N=` rules",
+      RuleKind::StartAnchor => "`(start)` on the left side",
+      RuleKind::EndAnchor => "`(end)` on the left side",
+      RuleKind::MoveToStart => "`(start)` on the right side",
+      RuleKind::MoveToEnd => "`(end)` on the right side",
+      RuleKind::Return => "`(return)`",
+    }
+  }
+
+  /// See if the rule is this type of rule
+  pub fn matches(&self, rule: &Rule) -> bool {
+    match self {
+      RuleKind::Once => rule.limit().is_some(),
+      RuleKind::StartAnchor => rule.anchor() == Anchor::Start,
+      RuleKind::EndAnchor => rule.anchor() == Anchor::End,
+      RuleKind::MoveToStart => rule.target() == Target::Start,
+      RuleKind::MoveToEnd => rule.target() == Target::End,
+      RuleKind::Return => rule.target() == Target::Return,
+    }
+  }
+}
+
+impl Constraints {
+  ///
+  /// Get a short description of every constraint to show to the player
+  ///
+  pub fn describe(&self) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(max_rules) = self.max_rules {
+      lines.push(format!("Maximum number of rules: {max_rules}"));
+    }
+    if let Some(max_rule_length) = self.max_rule_length {
+      lines.push(format!("Maximum characters in each rule: {max_rule_length}"));
+    }
+    if let Some(alphabet) = &self.left_alphabet {
+      lines.push(format!("Left side can only use the characters \"{alphabet}\""));
+    }
+    if let Some(alphabet) = &self.right_alphabet {
+      lines.push(format!("Right side can only use the characters \"{alphabet}\""));
+    }
+    if !self.banned_characters.is_empty() {
+      lines.push(format!("Cannot use the characters \"{}\"", self.banned_characters));
+    }
+    for kind in &self.forbidden_rules {
+      lines.push(format!("Cannot use {}", kind.description()));
+    }
+    lines
+  }
+
+  ///
+  /// Check the code against the constraints
+  ///   Returns a message for every broken constraint, which is empty if the code is allowed
+  ///
+  pub fn check(&self, code: &Program) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    let num_rules = code.rules().len();
+    if let Some(max_rules) = self.max_rules.filter(|max| num_rules > *max) {
+      errors.push(format!(
+        "Code has {num_rules} rules, but the level allows at most {max_rules}"
+      ));
+    }
+
+    for rule in code.rules() {
+      let mut problems = Vec::new();
+
+      let length = rule.left().chars().count() + rule.right().chars().count();
+      if let Some(max_rule_length) = self.max_rule_length.filter(|max| length > *max) {
+        problems.push(format!(
+          "has {length} characters, but the level allows at most {max_rule_length}"
+        ));
+      }
+
+      let sides = [
+        ("left", rule.left(), &self.left_alphabet),
+        ("right", rule.right(), &self.right_alphabet),
+      ];
+      for (side, text, alphabet) in sides {
+        if let Some(c) = text.chars().find(|c| self.banned_characters.contains(*c)) {
+          problems.push(format!("uses the banned character {c:?} on the {side} side"));
+        }
+        if let Some(alphabet) = alphabet {
+          if let Some(c) = text.chars().find(|c| !alphabet.contains(*c)) {
+            problems.push(format!(
+              "uses the character {c:?}, which is not allowed on the {side} side"
+            ));
+          }
+        }
+      }
+
+      for kind in self.forbidden_rules.iter().filter(|kind| kind.matches(rule)) {
+        problems.push(format!("uses {}, which is not allowed", kind.description()));
+      }
+
+      for problem in problems {
+        errors.push(format!("Line {}: {} {}", rule.line(), rule, problem));
+      }
+    }
+
+    errors
+  }
+}
@@ -0,0 +1,322 @@
+use std::io::{self, BufRead, Write};
+
+use crate::checker::Checker;
+use crate::program::{Outcome, Program, ProgramState, Step};
+use crate::trace::Verdict;
+
+static HELP_MESSAGE: &str = "\
+Commands:
+  s, step [n]         Run the next n rules (default 1)
+  b, back [n]         Go back n rules (default 1)
+  c, continue         Run until a breakpoint or the program finishes
+  r, run              Run until the program finishes, ignoring breakpoints
+  break line <n>      Stop before running the rule on line n
+  break match <text>  Stop when the string contains the text
+  breakpoints         List all breakpoints
+  delete <n>          Delete breakpoint n
+  state               Show how many times the limited rules have fired
+  p, print            Print the current string
+  rules               Print the rules in the program
+  restart             Go back to the input string
+  h, help             Show this help message
+  q, quit             Exit the debugger";
+
+/// Interactive debugger to step through a program one rule at a time
+pub struct Debugger<'a> {
+  program: &'a Program,
+  expected: Option<String>,
+  checker: Checker,
+  max_executions: usize,
+  history: Vec<Frame<'a>>,
+  breakpoints: Vec<Breakpoint>,
+  /// Number of executions where "continue" last stopped at a breakpoint, so the next "continue" can move past it
+  stopped_at_breakpoint: Option<usize>,
+}
+
+/// Snapshot of the program after every executed rule
+#[derive(Debug, Clone)]
+struct Frame<'a> {
+  string: String,
+  state: ProgramState,
+  step: Option<Step<'a>>,
+  finished: bool,
+}
+
+/// Condition to stop the program when using "continue"
+#[derive(Debug, Clone)]
+enum Breakpoint {
+  /// Stop before the rule on the given line number is applied
+  Line(usize),
+  /// Stop when the string contains the text
+  Match(String),
+}
+
+impl<'a> Debugger<'a> {
+  /// Construct a new debugger for the program and input string
+  pub fn new(program: &'a Program, input: impl Into<String>, expected: Option<String>, max_executions: usize) -> Self {
+    Self {
+      program,
+      expected,
+      checker: Checker::exact(),
+      max_executions,
+      history: vec![Frame {
+        string: input.into(),
+        state: ProgramState::new(),
+        step: None,
+        finished: false,
+      }],
+      breakpoints: Vec::new(),
+      stopped_at_breakpoint: None,
+    }
+  }
+
+  /// Use the level to decide if the final string is correct, instead of an exact match
+  pub fn with_checker(mut self, checker: Checker) -> Self {
+    self.checker = checker;
+    self
+  }
+
+  ///
+  /// Read and run debugger commands from standard input until the user quits
+  ///
+  pub fn run(&mut self) {
+    println!("----- Debugger: type 'help' for commands -----\n");
+    self.print_current();
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+      print!("(debug) ");
+      io::stdout().flush().ok();
+
+      let line = match lines.next() {
+        Some(Ok(line)) => line,
+        _ => break,
+      };
+
+      let mut words = line.split_whitespace();
+      let command = words.next().unwrap_or("");
+      let argument: Vec<&str> = words.collect();
+      let count = || argument.first().and_then(|n| n.parse::<usize>().ok()).unwrap_or(1);
+
+      match command {
+        "" => continue,
+        "s" | "step" => self.step_forward(count()),
+        "b" | "back" => self.step_back(count()),
+        "c" | "continue" => self.run_forward(true),
+        "r" | "run" => self.run_forward(false),
+        "break" => self.add_breakpoint(&argument),
+        "breakpoints" => self.print_breakpoints(),
+        "delete" => self.delete_breakpoint(&argument),
+        "state" => self.print_state(),
+        "p" | "print" => self.print_current(),
+        "rules" => self.print_rules(),
+        "restart" => self.step_back(self.executions()),
+        "h" | "help" => println!("{HELP_MESSAGE}\n"),
+        "q" | "quit" => break,
+        _ => println!("Unknown command '{command}', type 'help' for commands\n"),
+      }
+    }
+  }
+
+  fn current(&self) -> &Frame<'a> {
+    self.history.last().unwrap() // Will not fail, always has the input
+  }
+
+  /// Number of rules that have been executed so far
+  fn executions(&self) -> usize {
+    self.history.len() - 1
+  }
+
+  /// Apply the next rule, returns false if the program has finished
+  fn execute_next(&mut self) -> bool {
+    let current = self.current();
+    if current.finished {
+      return false;
+    }
+
+    let mut state = current.state.clone();
+    match self.program.execute_rule(&current.string, &mut state) {
+      None => {
+        self.history.last_mut().unwrap().finished = true;
+        false
+      },
+
+      Some(step) => {
+        self.history.push(Frame {
+          string: step.result().into(),
+          state,
+          finished: step.rule().halts(),
+          step: Some(step),
+        });
+        true
+      },
+    }
+  }
+
+  fn step_forward(&mut self, count: usize) {
+    for _ in 0..count {
+      if !self.execute_next() {
+        break;
+      }
+    }
+    self.print_current();
+  }
+
+  fn step_back(&mut self, count: usize) {
+    let keep = self.history.len().saturating_sub(count).max(1);
+    self.history.truncate(keep);
+    self.stopped_at_breakpoint = None;
+    if let Some(frame) = self.history.last_mut() {
+      frame.finished = frame.step.as_ref().is_some_and(|step| step.rule().halts());
+    }
+    self.print_current();
+  }
+
+  /// Run until the program finishes, times out, or hits a breakpoint
+  fn run_forward(&mut self, use_breakpoints: bool) {
+    // Only skip the breakpoint that the debugger is stopped at, if any
+    let skip = self.stopped_at_breakpoint.take();
+    while self.executions() < self.max_executions {
+      if use_breakpoints && skip != Some(self.executions()) {
+        if let Some(index) = self.hit_breakpoint() {
+          println!("Stopped at breakpoint {}", index + 1);
+          self.stopped_at_breakpoint = Some(self.executions());
+          break;
+        }
+      }
+
+      if !self.execute_next() {
+        break;
+      }
+    }
+
+    if self.executions() >= self.max_executions {
+      println!(
+        "Stopped after the maximum number of executions ({})",
+        self.max_executions
+      );
+    }
+    self.print_current();
+  }
+
+  /// Get the index of the first breakpoint for the current string, if any
+  fn hit_breakpoint(&self) -> Option<usize> {
+    let current = self.current();
+    let next_line = {
+      let mut state = current.state.clone();
+      self
+        .program
+        .execute_rule(&current.string, &mut state)
+        .map(|step| step.rule().line())
+    };
+
+    self.breakpoints.iter().position(|breakpoint| match breakpoint {
+      Breakpoint::Line(line) => next_line == Some(*line),
+      Breakpoint::Match(text) => current.string.contains(text.as_str()),
+    })
+  }
+
+  fn add_breakpoint(&mut self, argument: &[&str]) {
+    let breakpoint = match argument {
+      ["line", line] => match line.parse() {
+        Ok(line) if self.program.rules().iter().any(|r| r.line() == line) => Breakpoint::Line(line),
+        _ => return println!("No rule on line '{line}'\n"),
+      },
+      ["match", text @ ..] if !text.is_empty() => Breakpoint::Match(text.join(" ")),
+      _ => return println!("Usage: break line <n> | break match <text>\n"),
+    };
+
+    self.breakpoints.push(breakpoint);
+    self.print_breakpoints();
+  }
+
+  fn delete_breakpoint(&mut self, argument: &[&str]) {
+    match argument.first().and_then(|n| n.parse::<usize>().ok()) {
+      Some(n) if n >= 1 && n <= self.breakpoints.len() => {
+        self.breakpoints.remove(n - 1);
+        self.print_breakpoints();
+      },
+      _ => println!("Usage: delete <n>, where n is from the list of breakpoints\n"),
+    }
+  }
+
+  fn print_breakpoints(&self) {
+    if self.breakpoints.is_empty() {
+      return println!("No breakpoints\n");
+    }
+
+    for (breakpoint, number) in self.breakpoints.iter().zip(1..) {
+      match breakpoint {
+        Breakpoint::Line(line) => println!("  {number}: line {line}"),
+        Breakpoint::Match(text) => println!("  {number}: match '{text}'"),
+      }
+    }
+    println!();
+  }
+
+  /// Show the limited rules and how many times they have fired
+  fn print_state(&self) {
+    let state = &self.current().state;
+    let mut any_limited = false;
+
+    for (rule, rule_index) in self.program.rules().iter().zip(0..) {
+      if let Some(limit) = rule.limit() {
+        let count = state.count(rule_index);
+        let status = if count >= limit { " (used up)" } else { "" };
+        println!("  Line {}: {}  fired {count}/{limit}{status}", rule.line(), rule);
+        any_limited = true;
+      }
+    }
+
+    if !any_limited {
+      println!("No limited rules in the program");
+    }
+    println!();
+  }
+
+  fn print_rules(&self) {
+    for rule in self.program.rules() {
+      println!("  Line {}: {}", rule.line(), rule);
+    }
+    println!();
+  }
+
+  /// Show the last rule applied and the current string
+  fn print_current(&self) {
+    let current = self.current();
+
+    match &current.step {
+      None => println!("Step 0: Input"),
+      Some(step) => println!(
+        "Step {}: Rule (line {}): {}",
+        self.executions(),
+        step.rule().line(),
+        step.rule()
+      ),
+    }
+    println!("  {}", current.string);
+
+    if current.finished {
+      println!("Finished");
+      if let Some(expected) = &self.expected {
+        let input = &self.history[0].string;
+        let outcome = Outcome::Finished(current.string.clone());
+        match self.checker.verdict(input, &outcome, expected) {
+          (Verdict::Passed, _) if self.checker.has_check() => println!("Output was accepted by the level"),
+          (Verdict::Passed, _) => println!("Output matches the expected output"),
+          (_, Some(reason)) => {
+            println!("Error! Output was not accepted by the level");
+            println!("  Reason:   {reason}");
+            println!("  Example:  {expected}");
+          },
+          (_, None) => {
+            println!("Error! String does not match expected output");
+            println!("  Expected: {expected}");
+          },
+        }
+      }
+    }
+    println!();
+  }
+}
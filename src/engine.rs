@@ -0,0 +1,294 @@
+use aho_corasick::{AhoCorasick, Input};
+use std::collections::HashMap;
+
+use crate::program::{Anchor, Program, ProgramState, Step};
+
+///
+/// Optimized executor that finds every rule match in a single scan of the string
+///   Gives the same results as `Program::execute_rule`, but it uses an Aho-Corasick
+///   automaton over all of the left sides instead of searching once per rule.
+///
+#[derive(Debug, Clone)]
+pub struct Engine<'a> {
+  program: &'a Program,
+  automaton: AhoCorasick,
+  rule_patterns: Vec<Option<usize>>,
+  pattern_lengths: Vec<usize>,
+  max_pattern_length: usize,
+}
+
+///
+/// Leftmost match for every pattern in the last string seen by the engine
+///   Each test case should use a separate cache.
+///
+#[derive(Debug, Clone, Default)]
+pub struct MatchCache {
+  text: Option<String>,
+  positions: Vec<Option<usize>>,
+}
+
+/// What needs to happen to a cached position after the string changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Update {
+  /// Match is before the edit, so it is still the leftmost match
+  Keep,
+  /// Only a match that overlaps the edit can come before this position
+  Window(Option<usize>),
+  /// Old match overlapped the edit, so search everything after the edit
+  Rescan,
+}
+
+impl<'a> Engine<'a> {
+  /// Build the engine for the program
+  pub fn new(program: &'a Program) -> Self {
+    let mut patterns: Vec<&str> = Vec::new();
+    let mut pattern_ids: HashMap<&str, usize> = HashMap::new();
+
+    // Only non-empty left sides without an anchor need to be searched
+    //  Rules with the same left side share a single pattern
+    let rule_patterns = program
+      .rules()
+      .iter()
+      .map(|rule| {
+        if rule.anchor() != Anchor::Anywhere || rule.left().is_empty() {
+          return None;
+        }
+
+        Some(*pattern_ids.entry(rule.left()).or_insert_with(|| {
+          patterns.push(rule.left());
+          patterns.len() - 1
+        }))
+      })
+      .collect();
+
+    let pattern_lengths: Vec<usize> = patterns.iter().map(|p| p.len()).collect();
+    let max_pattern_length = pattern_lengths.iter().cloned().max().unwrap_or(0);
+    let automaton = AhoCorasick::new(&patterns).expect("Failed to build Aho-Corasick automaton");
+
+    Self {
+      program,
+      automaton,
+      rule_patterns,
+      pattern_lengths,
+      max_pattern_length,
+    }
+  }
+
+  ///
+  /// Execute the first matching rule found, returns None if no rules matched
+  ///   This method requires a program state and match cache to be stored between invocations
+  ///
+  pub fn execute_rule(&self, input: &str, state: &mut ProgramState, cache: &mut MatchCache) -> Option<Step<'a>> {
+    self.update_cache(input, cache);
+
+    for (rule, rule_index) in self.program.rules().iter().zip(0..) {
+      // See if the rule matches the input string
+      let offset = match self.rule_patterns[rule_index] {
+        Some(pattern) => cache.positions[pattern],
+        None => rule.find(input),
+      };
+      let offset = match offset {
+        None => continue,
+        Some(offset) => offset,
+      };
+
+      if !state.try_count(rule_index, rule.limit()) {
+        continue;
+      }
+
+      return Some(Step::new(rule, rule_index, offset, rule.apply(input, offset)));
+    }
+
+    None
+  }
+
+  /// Update the cached matches to the new string
+  fn update_cache(&self, input: &str, cache: &mut MatchCache) {
+    let old = match cache.text.take() {
+      Some(old) => old,
+      None => {
+        cache.positions = self.scan(input, 0, input.len(), |_| true);
+        cache.text = Some(input.into());
+        return;
+      },
+    };
+
+    // Find the region of the string that changed
+    let prefix = old.bytes().zip(input.bytes()).take_while(|(a, b)| a == b).count();
+    let max_suffix = old.len().min(input.len()) - prefix;
+    let suffix = old
+      .bytes()
+      .rev()
+      .zip(input.bytes().rev())
+      .take(max_suffix)
+      .take_while(|(a, b)| a == b)
+      .count();
+
+    let removed = old.len() - prefix - suffix;
+    let inserted = input.len() - prefix - suffix;
+
+    // Figure out which cached positions are still valid
+    let updates: Vec<Update> = cache
+      .positions
+      .iter()
+      .zip(self.pattern_lengths.iter())
+      .map(|(position, length)| match *position {
+        Some(position) if position + length <= prefix => Update::Keep,
+        Some(position) if position >= prefix + removed => Update::Window(Some(position - removed + inserted)),
+        Some(_) => Update::Rescan,
+        None => Update::Window(None),
+      })
+      .collect();
+
+    // Any new match must overlap the edited region
+    //  Only rescan the rest of the string if an old match was destroyed
+    let window_start = prefix.saturating_sub(self.max_pattern_length.saturating_sub(1));
+    let window_end = input
+      .len()
+      .min(prefix + inserted + self.max_pattern_length.saturating_sub(1));
+    let scan_end = if updates.contains(&Update::Rescan) {
+      input.len()
+    } else {
+      window_end
+    };
+
+    let found = self.scan(input, window_start, scan_end, |pattern| {
+      updates[pattern] != Update::Keep
+    });
+
+    for ((position, update), found) in cache.positions.iter_mut().zip(updates).zip(found) {
+      *position = match update {
+        Update::Keep => *position,
+        Update::Rescan => found,
+        Update::Window(shifted) => match (found, shifted) {
+          (Some(found), Some(shifted)) => Some(found.min(shifted)),
+          (found, shifted) => found.or(shifted),
+        },
+      };
+    }
+
+    cache.text = Some(input.into());
+  }
+
+  ///
+  /// Find the leftmost match of every needed pattern within the range
+  ///   Stops early once every needed pattern has been found
+  ///
+  fn scan(&self, input: &str, start: usize, end: usize, needed: impl Fn(usize) -> bool) -> Vec<Option<usize>> {
+    let mut positions = vec![None; self.pattern_lengths.len()];
+    let mut remaining = (0..positions.len()).filter(|p| needed(*p)).count();
+    if remaining == 0 || start >= end {
+      return positions;
+    }
+
+    // Overlapping matches are reported in order of the end position,
+    //  so the first match for any pattern is also the leftmost match
+    let search = Input::new(input).span(start..end);
+    for found in self.automaton.find_overlapping_iter(search) {
+      let pattern = found.pattern().as_usize();
+      if positions[pattern].is_none() && needed(pattern) {
+        positions[pattern] = Some(found.start());
+        remaining -= 1;
+        if remaining == 0 {
+          break;
+        }
+      }
+    }
+
+    positions
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use rand::prelude::*;
+  use rand_pcg::Pcg64;
+
+  use super::*;
+  use crate::program::{Rule, Target};
+
+  const ALPHABET: &[char] = &['a', 'b', 'c'];
+  const MAX_STEPS: usize = 200;
+
+  fn random_string<R: Rng>(rng: &mut R, max_length: usize) -> String {
+    let length = rng.gen_range(0..=max_length);
+    (0..length).map(|_| *ALPHABET.choose(rng).unwrap()).collect()
+  }
+
+  fn random_program<R: Rng>(rng: &mut R) -> Program {
+    let rules = (0..rng.gen_range(1..=6))
+      .map(|_| {
+        let limit = [None, None, None, Some(1), Some(3)].choose(rng).cloned().unwrap();
+        let anchor = [Anchor::Anywhere, Anchor::Anywhere, Anchor::Start, Anchor::End]
+          .choose(rng)
+          .cloned()
+          .unwrap();
+        let target = [
+          Target::Replace,
+          Target::Replace,
+          Target::Start,
+          Target::End,
+          Target::Return,
+        ]
+        .choose(rng)
+        .cloned()
+        .unwrap();
+
+        Rule::new(random_string(rng, 3), random_string(rng, 3), limit)
+          .with_anchor(anchor)
+          .with_target(target)
+      })
+      .collect();
+
+    Program::new(rules)
+  }
+
+  /// Run the program one step at a time, returning every string
+  fn trace(
+    input: &str,
+    mut execute: impl FnMut(&str) -> Option<(String, usize, usize)>,
+  ) -> Vec<(String, usize, usize)> {
+    let mut input = input.to_string();
+    let mut steps = Vec::new();
+
+    while steps.len() < MAX_STEPS {
+      match execute(&input) {
+        None => break,
+        Some(step) => {
+          input = step.0.clone();
+          steps.push(step);
+        },
+      }
+    }
+
+    steps
+  }
+
+  #[test]
+  fn engine_matches_naive_execution() {
+    let mut rng = Pcg64::seed_from_u64(12345);
+
+    for _ in 0..1000 {
+      let program = random_program(&mut rng);
+      let engine = Engine::new(&program);
+      let input = random_string(&mut rng, 16);
+
+      let mut state = ProgramState::new();
+      let expected = trace(&input, |input| {
+        program
+          .execute_rule(input, &mut state)
+          .map(|s| (s.result().to_string(), s.rule_index(), s.offset()))
+      });
+
+      let mut state = ProgramState::new();
+      let mut cache = MatchCache::default();
+      let actual = trace(&input, |input| {
+        engine
+          .execute_rule(input, &mut state, &mut cache)
+          .map(|s| (s.result().to_string(), s.rule_index(), s.offset()))
+      });
+
+      assert_eq!(actual, expected, "input '{input}' with program:\n{program:?}");
+    }
+  }
+}
@@ -0,0 +1,69 @@
+use rand::prelude::*;
+use rand_pcg::Pcg64;
+use std::error::Error;
+
+use crate::pack_source::PackSource;
+use crate::script::LevelScript;
+
+/// Maximum number of random edits to make to a generated input
+const MAX_EDITS: usize = 3;
+
+///
+/// Generate random test cases beyond the fixed ones used to validate the code
+///   Uses the generator with a different seed. If the level defines `solve(input)`,
+///   every other input also gets a few random edits, as long as the level accepts the edited input.
+///
+pub fn fuzz_test_cases(
+  lua_file: &str,
+  pack_source: &PackSource,
+  seed: u32,
+  n: usize,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+  let script = LevelScript::load(lua_file, pack_source, seed)?;
+  let mut test_cases = script.generate_test_cases(n)?;
+
+  if script.has_solve() {
+    let mut rng = Pcg64::seed_from_u64(seed.into());
+    for (input, output) in test_cases.iter_mut().skip(1).step_by(2) {
+      let edited = random_edits(input, &mut rng);
+      if let Ok(solved) = script.solve(&edited) {
+        *input = edited;
+        *output = solved;
+      }
+    }
+  }
+
+  Ok(test_cases)
+}
+
+///
+/// Insert, remove, or replace a few random characters in the input
+///   New characters are picked from the ones already in the input
+///
+fn random_edits<R: Rng>(input: &str, rng: &mut R) -> String {
+  let mut chars: Vec<char> = input.chars().collect();
+  let mut alphabet = chars.clone();
+  alphabet.sort_unstable();
+  alphabet.dedup();
+
+  for _ in 0..rng.gen_range(1..=MAX_EDITS) {
+    let letter = match alphabet.choose(rng) {
+      Some(letter) => *letter,
+      None => break,
+    };
+
+    match rng.gen_range(0..3) {
+      0 => chars.insert(rng.gen_range(0..=chars.len()), letter),
+      1 if !chars.is_empty() => {
+        chars.remove(rng.gen_range(0..chars.len()));
+      },
+      _ if !chars.is_empty() => {
+        let index = rng.gen_range(0..chars.len());
+        chars[index] = letter;
+      },
+      _ => {},
+    }
+  }
+
+  chars.into_iter().collect()
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::level_pack::{self, LevelPack};
+use crate::pack_source::{self, ARCHIVE_EXTENSION};
+
+///
+/// Install a level pack archive for the current user
+///   The archive must load as a valid level pack with all of its Lua files before it is copied.
+///   Installing a pack again replaces the archive that has the same pack ID.
+///   Returns the pack along with the path to the installed archive
+///
+pub fn install_pack(archive_file: &Path) -> io::Result<(LevelPack, PathBuf)> {
+  let pack = LevelPack::from_archive(archive_file)?;
+  let missing = pack.missing_lua_files();
+  if !missing.is_empty() {
+    return Err(io::Error::new(
+      ErrorKind::InvalidData,
+      format!("level pack is missing Lua files: {}", missing.join(", ")),
+    ));
+  }
+
+  let folder = user_packs_folder()?;
+  fs::create_dir_all(&folder)?;
+  let installed = folder.join(format!("{}.{ARCHIVE_EXTENSION}", file_name(pack.id())));
+  fs::copy(archive_file, &installed)?;
+  Ok((pack, installed))
+}
+
+///
+/// Remove every installed level pack archive with the pack ID
+///   Returns the archive files that were removed, which is empty if the pack was not installed
+///
+pub fn uninstall_pack(id: &str) -> io::Result<Vec<PathBuf>> {
+  let folder = user_packs_folder()?;
+  if !folder.is_dir() {
+    return Ok(Vec::new());
+  }
+
+  let mut removed = Vec::new();
+  for entry in fs::read_dir(folder)? {
+    let path = entry?.path();
+    if !pack_source::is_archive(&path) {
+      continue;
+    }
+
+    // Archives that no longer load cannot match the ID
+    if LevelPack::from_archive(&path).is_ok_and(|pack| pack.id() == id) {
+      fs::remove_file(&path)?;
+      removed.push(path);
+    }
+  }
+
+  Ok(removed)
+}
+
+fn user_packs_folder() -> io::Result<PathBuf> {
+  level_pack::user_packs_folder()
+    .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "could not find the user data folder"))
+}
+
+/// Turn the pack ID, or any other name, into a safe file name
+pub fn file_name(name: &str) -> String {
+  name
+    .chars()
+    .map(|c| {
+      if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+        c
+      } else {
+        '_'
+      }
+    })
+    .collect()
+}
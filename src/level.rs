@@ -1,17 +1,30 @@
 use rand::prelude::*;
-use rlua::prelude::*;
+use rayon::prelude::*;
 use serde::Deserialize;
 use std::error::Error;
-use std::fs;
 
-use crate::level_pack::PACKS_FOLDER;
-use crate::program::{Program, ProgramState};
+use crate::checker::Checker;
+use crate::constraints::Constraints;
+use crate::fuzz;
+use crate::pack_source::PackSource;
+use crate::program::{Outcome, Program};
+use crate::score::Score;
+use crate::script::LevelScript;
+use crate::shrink;
+use crate::trace::{CaseTrace, TraceWriter, Verdict};
 
+// Defaults for any settings that are not set by the level or the level pack
 const NUM_EXAMPLES: usize = 5;
 const NUM_TEST_CASES: usize = 100;
-const MAX_EXECUTIONS: usize = 100_000; /* 100 Thousand */
+pub const MAX_EXECUTIONS: usize = 100_000; /* 100 Thousand */
 const TEST_CASE_SEED: u32 = 12345;
 
+/// Test case number for an input string that is not one of the level's test cases
+const CUSTOM_INPUT: usize = 0;
+
+/// Maximum number of characters to show for each input in the summary table
+const SUMMARY_INPUT_WIDTH: usize = 40;
+
 /// Single entry in the levels.json file
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -19,6 +32,44 @@ pub struct Level {
   name: String,
   description: String,
   lua_file: String,
+  #[serde(default)]
+  edge_cases: Vec<EdgeCase>,
+  #[serde(default)]
+  constraints: Constraints,
+  /// Target number of rules for an extra star
+  par_lines: Option<usize>,
+  /// Target average number of steps across the test cases for an extra star
+  par_steps: Option<usize>,
+  #[serde(flatten)]
+  settings: LevelSettings,
+}
+
+/// Hand-written test case from the pack file, which uses `solve(input)` if there is no output
+#[derive(Debug, Clone, Deserialize)]
+pub struct EdgeCase {
+  input: String,
+  output: Option<String>,
+}
+
+///
+/// Settings for testing a level, which can be set for each level or for the whole level pack
+///   Settings on the level override the ones from the pack,
+///   and anything not set in either place uses the default value
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LevelSettings {
+  test_cases: Option<usize>,
+  examples: Option<usize>,
+  max_steps: Option<usize>,
+  seed: Option<u32>,
+}
+
+/// Runs the code on the test cases for a level
+struct TestRunner<'a> {
+  code: &'a Program,
+  checker: Checker,
+  max_steps: usize,
 }
 
 #[allow(unused)]
@@ -29,6 +80,11 @@ impl Level {
       name: name.into(),
       description: description.into(),
       lua_file: lua_file.into(),
+      edge_cases: Vec::new(),
+      constraints: Constraints::default(),
+      par_lines: None,
+      par_steps: None,
+      settings: LevelSettings::default(),
     }
   }
 
@@ -44,17 +100,53 @@ impl Level {
     &self.lua_file
   }
 
+  pub fn constraints(&self) -> &Constraints {
+    &self.constraints
+  }
+
+  pub fn par_lines(&self) -> Option<usize> {
+    self.par_lines
+  }
+
+  pub fn par_steps(&self) -> Option<usize> {
+    self.par_steps
+  }
+
+  pub fn settings(&self) -> &LevelSettings {
+    &self.settings
+  }
+
+  /// Use the level pack settings for anything that is not set on the level
+  pub fn inherit_settings(&mut self, pack_settings: &LevelSettings) {
+    self.settings = self.settings.or(pack_settings);
+  }
+
   ///
   /// Print the full level details along with some examples
   ///
-  pub fn print_level_details(&self, level_number: usize, level_code: &str, parent_folder: &str) {
+  pub fn print_level_details(&self, level_number: usize, level_code: &str, pack_source: &PackSource) {
     println!("Level {}: {}", level_number, self.name);
     println!("  Code: {}\n", level_code);
     println!("{}\n", self.description);
 
+    let constraints = self.constraints.describe();
+    if !constraints.is_empty() {
+      println!("Constraints:");
+      for constraint in constraints {
+        println!("  - {constraint}");
+      }
+      println!();
+    }
+
     println!("Examples:\n");
 
-    let test_cases = match self.generate_test_cases(thread_rng().gen(), NUM_EXAMPLES, parent_folder) {
+    // Always show the edge cases, along with some random test cases
+    let test_cases = self.edge_cases(pack_source).and_then(|mut test_cases| {
+      let examples = self.generate_test_cases(thread_rng().gen(), self.settings.examples(), pack_source)?;
+      test_cases.extend(examples);
+      Ok(test_cases)
+    });
+    let test_cases = match test_cases {
       Ok(t) => t,
       Err(e) => {
         println!("Failed to load and run Lua file: {}", e);
@@ -68,55 +160,297 @@ impl Level {
     }
   }
 
+  ///
+  /// Make sure the code follows the level constraints before running it
+  ///   Prints every broken constraint and returns false if the code is not allowed
+  ///
+  pub fn check_constraints(&self, code: &Program) -> bool {
+    let errors = self.constraints.check(code);
+    if errors.is_empty() {
+      return true;
+    }
+
+    println!("Error! Code does not follow the level constraints:");
+    for error in errors {
+      println!("  {error}");
+    }
+    println!();
+    false
+  }
+
   ///
   /// See if the given rules passes all of the test cases
+  ///   Stops at the first failing test case unless `run_all` is set,
+  ///   in which case it runs every test case and prints a summary instead.
+  ///   Also writes a trace of every test case that runs if a trace writer is given,
+  ///   and searches for a smaller failing input if `shrink` is set.
+  ///   Returns the score for the code if every test case passed
   ///
-  pub fn validate_code(&self, code: &Program, parent_folder: &str) -> bool {
-    let test_cases = match self.generate_test_cases(TEST_CASE_SEED, NUM_TEST_CASES, parent_folder) {
+  pub fn validate_code(
+    &self,
+    code: &Program,
+    pack_source: &PackSource,
+    run_all: bool,
+    shrink: bool,
+    mut trace: Option<&mut TraceWriter>,
+  ) -> Option<Score> {
+    let test_cases = match self.all_test_cases(pack_source) {
       Ok(t) => t,
       Err(e) => {
         println!("Failed to load and run Lua file: {}", e);
-        return false;
+        return None;
       },
     };
+    let runner = self.test_runner(code, pack_source)?;
 
-    // Run through the test cases one-by-one
-    for ((mut input, output), test_case_number) in test_cases.into_iter().zip(1..) {
-      println!("===== Test case {test_case_number}: =====\n  Input:  {input}\n  Output: {output}\n");
-
-      // Keep applying executions until no more to apply or we time out
-      let mut execution = 0;
-      let mut state = ProgramState::new();
-      while execution < MAX_EXECUTIONS {
-        input = match code.execute_rule(&input, &mut state) {
-          None => break,
-          Some((new_string, rule)) => {
-            println!("Rule: {rule}\n{new_string}\n");
-            new_string
-          },
-        };
+    // Only show the individual steps when stopping at the first failure
+    //  The steps are printed as they run, so first find the first failing test case in parallel without any output.
+    //  Then only the test cases up to that one run again, one at a time, to print their steps in order
+    let verbose = !run_all;
+    let numbered: Vec<_> = test_cases.into_iter().zip(1..).collect();
+    let cases = if verbose {
+      let first_failure = numbered
+        .par_iter()
+        .position_first(|((input, output), _)| runner.verdict(input, output) != Verdict::Passed);
+      &numbered[..first_failure.map_or(numbered.len(), |index| index + 1)]
+    } else {
+      &numbered[..]
+    };
 
-        execution += 1;
+    // Otherwise run the test cases in parallel, one batch at a time, and then handle the results in order
+    let batch_size = if verbose {
+      1
+    } else {
+      rayon::current_num_threads().max(1)
+    };
+    let mut results = Vec::new();
+
+    for batch in cases.chunks(batch_size) {
+      let run_case = |((input, output), test_case_number): &((String, String), usize)| {
+        if verbose {
+          println!("===== Test case {}: =====", test_case_number);
+          println!("  Input:  {}\n  Output: {}\n", input, output);
+        }
+        let case_trace = trace.as_ref().map(|t| t.begin_case(*test_case_number, input, output));
+        runner.run_test_case(*test_case_number, input, output, verbose, case_trace)
+      };
+      let batch_results: Vec<_> = if verbose {
+        batch.iter().map(run_case).collect()
+      } else {
+        batch.par_iter().map(run_case).collect()
+      };
+
+      for (result, case_trace) in batch_results {
+        if let (Some(writer), Some(case_trace)) = (trace.as_mut(), case_trace) {
+          if let Err(e) = writer.write_case(case_trace) {
+            println!(
+              "Warning: failed to write trace file, no more test cases will be traced: {}",
+              e
+            );
+            trace = None;
+          }
+        }
+
+        if verbose && result.verdict != Verdict::Passed {
+          if shrink {
+            self.print_smaller_failure(&runner, pack_source, &result);
+          }
+          return None;
+        }
+        results.push(result);
       }
+    }
 
-      // Print error if the execution timed out
-      if execution >= MAX_EXECUTIONS {
-        println!("Error! Program exceeded maximum number of executions ({MAX_EXECUTIONS})");
-        return false;
+    if run_all {
+      print_summary(&results, &runner);
+      if let (true, Some(smallest)) = (shrink, smallest_failure(&results)) {
+        self.print_smaller_failure(&runner, pack_source, smallest);
       }
+    }
+
+    // See if all test cases passed
+    if results.iter().any(|r| r.verdict != Verdict::Passed) {
+      return None;
+    }
+
+    let steps: Vec<_> = results.iter().map(|r| r.steps).collect();
+    Some(Score::new(code.rules().len(), &steps))
+  }
+
+  ///
+  /// Run the code on random test cases beyond the fixed ones from `validate_code`
+  ///   Stops at the first failing test case, and searches for a smaller failing input if `shrink` is set
+  ///
+  pub fn fuzz_code(&self, code: &Program, pack_source: &PackSource, count: usize, shrink: bool) -> bool {
+    let seed: u32 = thread_rng().gen();
+    println!("===== Fuzzing: {count} random test cases (seed {seed}) =====\n");
 
-      println!("Finished");
-      if input != output {
-        println!("Error! String does not match expected output");
-        println!("  Given:    {input}");
-        println!("  Expected: {output}\n");
+    let test_cases = match fuzz::fuzz_test_cases(&self.lua_file, pack_source, seed, count) {
+      Ok(t) => t,
+      Err(e) => {
+        println!("Failed to load and run Lua file: {}", e);
         return false;
-      }
-      println!("Passed test case {test_case_number}\n");
+      },
+    };
+    let runner = match self.test_runner(code, pack_source) {
+      Some(runner) => runner,
+      None => return false,
+    };
+
+    // Only show the steps for the first failing test case
+    let numbered: Vec<_> = test_cases.into_iter().zip(1..).collect();
+    let failure = numbered
+      .par_iter()
+      .find_first(|((input, output), test_case_number)| runner.verdict(input, output) != Verdict::Passed);
+
+    let ((input, output), test_case_number) = match failure {
+      None => {
+        println!("Passed all {count} random test cases\n");
+        return true;
+      },
+      Some(failure) => failure,
+    };
+
+    println!("===== Random test case {test_case_number}: =====");
+    println!("  Input:  {input}\n  Output: {output}\n");
+    let (result, _) = runner.run_test_case(*test_case_number, input, output, true, None);
+    if shrink {
+      self.print_smaller_failure(&runner, pack_source, &result);
+    }
+
+    false
+  }
+
+  ///
+  /// Run the code on any input string, using the level to get the expected output
+  ///   Returns true if the code gives the expected output
+  ///
+  pub fn run_input(&self, code: &Program, pack_source: &PackSource, input: &str, shrink: bool) -> bool {
+    let output = match self.solve(input, pack_source) {
+      Ok(output) => output,
+      Err(e) => {
+        println!("Cannot get the expected output for input '{input}': {}", e);
+        return false;
+      },
+    };
+
+    let runner = match self.test_runner(code, pack_source) {
+      Some(runner) => runner,
+      None => return false,
+    };
+
+    println!("===== Input: =====");
+    println!("  Input:  {input}\n  Output: {output}\n");
+    let (result, _) = runner.run_test_case(CUSTOM_INPUT, input, &output, true, None);
+    if shrink && result.verdict != Verdict::Passed {
+      self.print_smaller_failure(&runner, pack_source, &result);
+    }
+
+    result.verdict == Verdict::Passed
+  }
+
+  ///
+  /// Get the expected output for any input string
+  ///   Fails if the level does not define `solve(input)` or the input is not valid for the level
+  ///
+  pub fn solve(&self, input: &str, pack_source: &PackSource) -> Result<String, Box<dyn Error>> {
+    LevelScript::load(&self.lua_file, pack_source, self.settings.seed())?.solve(input)
+  }
+
+  ///
+  /// Search for a smaller input that the code still fails on and show all of its steps
+  ///
+  fn print_smaller_failure(&self, runner: &TestRunner, pack_source: &PackSource, failure: &CaseResult) {
+    println!("Searching for a smaller failing input...\n");
+    let smaller = shrink::shrink(
+      self,
+      pack_source,
+      &failure.input,
+      &failure.expected,
+      failure.verdict,
+      |input, output| runner.verdict(input, output),
+    );
+    let (input, output) = match smaller {
+      Ok(Some(smaller)) => smaller,
+      Ok(None) => return println!("No smaller failing input found\n"),
+      Err(e) => return println!("Failed to search for a smaller failing input: {}\n", e),
+    };
+
+    println!("===== Smaller failing input: =====");
+    println!("  Input:  {input}\n  Output: {output}\n");
+    runner.run_test_case(failure.number, &input, &output, true, None);
+  }
+
+  ///
+  /// Get ready to run the code on the level's test cases, printing any errors
+  ///
+  fn test_runner<'a>(&self, code: &'a Program, pack_source: &PackSource) -> Option<TestRunner<'a>> {
+    Some(TestRunner {
+      code,
+      checker: self.load_checker(pack_source)?,
+      max_steps: self.settings.max_steps(),
+    })
+  }
+
+  ///
+  /// Load the level's `check(input, output)` function, printing any errors
+  ///
+  pub fn load_checker(&self, pack_source: &PackSource) -> Option<Checker> {
+    match Checker::load(&self.lua_file, pack_source) {
+      Ok(checker) => Some(checker),
+      Err(e) => {
+        println!("Failed to load and run Lua file: {}", e);
+        None
+      },
+    }
+  }
+
+  ///
+  /// Get a single test case that is used when validating the code
+  ///   Test cases are numbered starting from 1
+  ///
+  pub fn get_test_case(&self, number: usize, pack_source: &PackSource) -> Result<(String, String), Box<dyn Error>> {
+    let mut test_cases = self.all_test_cases(pack_source)?;
+    if number == 0 || number > test_cases.len() {
+      return Err(format!("test case must be between 1 and {}", test_cases.len()).into());
     }
 
-    // All test cases passed
-    true
+    Ok(test_cases.swap_remove(number - 1))
+  }
+
+  ///
+  /// Get every test case used to validate the code
+  ///   The edge cases always run first, followed by the generated test cases
+  ///
+  fn all_test_cases(&self, pack_source: &PackSource) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut test_cases = self.edge_cases(pack_source)?;
+    test_cases.extend(self.generate_test_cases(self.settings.seed(), self.settings.test_cases(), pack_source)?);
+    Ok(test_cases)
+  }
+
+  ///
+  /// Get the hand-written test cases from the pack file and the Lua `edgeCases()` function
+  ///   Uses `solve(input)` for any edge cases that do not have an output
+  ///
+  fn edge_cases(&self, pack_source: &PackSource) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let script = LevelScript::load(&self.lua_file, pack_source, self.settings.seed())?;
+    let from_script = script.edge_cases()?;
+
+    self
+      .edge_cases
+      .iter()
+      .chain(&from_script)
+      .map(|edge_case| {
+        let input = edge_case.input.clone();
+        match &edge_case.output {
+          Some(output) => Ok((input, output.clone())),
+          None => match script.solve(&input) {
+            Ok(output) => Ok((input, output)),
+            Err(e) => Err(format!("edge case '{input}' does not have an output: {e}").into()),
+          },
+        }
+      })
+      .collect()
   }
 
   ///
@@ -126,41 +460,242 @@ impl Level {
     &self,
     seed: u32,
     n: usize,
-    parent_folder: &str,
+    pack_source: &PackSource,
   ) -> Result<Vec<(String, String)>, Box<dyn Error>> {
-    // Try to load the Lua code file into memory
-    let lua_code = fs::read_to_string(format!("{PACKS_FOLDER}/{parent_folder}/{}", self.lua_file))?;
-
-    // Generate and run the code within the Lua context
-    let test_cases = Lua::new().context::<_, LuaResult<Vec<(String, String)>>>(|ctx| {
-      let globals = ctx.globals();
-
-      // Add the levels folder to the path
-      ctx
-        .load(&format!(
-          r#"package.path = "./{PACKS_FOLDER}/{parent_folder}/?.lua;" .. package.path"#
-        ))
-        .exec()?;
-
-      // Seed the random number generator
-      globals
-        .get::<_, LuaTable>("math")?
-        .get::<_, LuaFunction>("randomseed")?
-        .call::<_, ()>(seed)?;
-
-      // Load the script code
-      //  This should define a global function named "generateTestCase"
-      ctx.load(&lua_code).exec()?;
-
-      // Generate the test cases one-by-one
-      let generate_test_case: LuaFunction = globals.get("generateTestCase")?;
-      let test_cases = (0..n)
-        .map(|_| generate_test_case.call(()))
-        .collect::<Result<Vec<(String, String)>, _>>()?;
+    LevelScript::load(&self.lua_file, pack_source, seed)?.generate_test_cases(n)
+  }
+}
 
-      Ok(test_cases)
-    })?;
+#[allow(unused)]
+impl LevelSettings {
+  /// Number of test cases that the code must pass
+  pub fn test_cases(&self) -> usize {
+    self.test_cases.unwrap_or(NUM_TEST_CASES)
+  }
 
-    Ok(test_cases)
+  /// Number of examples to show in the level details
+  pub fn examples(&self) -> usize {
+    self.examples.unwrap_or(NUM_EXAMPLES)
+  }
+
+  /// Maximum number of rules to run for a single test case before timing out
+  pub fn max_steps(&self) -> usize {
+    self.max_steps.unwrap_or(MAX_EXECUTIONS)
+  }
+
+  /// Random seed for generating the test cases
+  pub fn seed(&self) -> u32 {
+    self.seed.unwrap_or(TEST_CASE_SEED)
+  }
+
+  /// Combine with another set of settings, which is only used for settings that are not set here
+  pub fn or(&self, defaults: &LevelSettings) -> LevelSettings {
+    LevelSettings {
+      test_cases: self.test_cases.or(defaults.test_cases),
+      examples: self.examples.or(defaults.examples),
+      max_steps: self.max_steps.or(defaults.max_steps),
+      seed: self.seed.or(defaults.seed),
+    }
+  }
+
+  /// Make sure the settings can be used to test a level
+  pub fn validate(&self) -> Result<(), String> {
+    if self.test_cases == Some(0) {
+      return Err("testCases must be at least 1".into());
+    }
+    if self.max_steps == Some(0) {
+      return Err("maxSteps must be at least 1".into());
+    }
+    Ok(())
+  }
+}
+
+impl TestRunner<'_> {
+  ///
+  /// Run the code on a single test case
+  ///   Prints each step as it runs and the final result if `verbose` is set.
+  ///   Returns the result along with the finished trace
+  ///
+  fn run_test_case(
+    &self,
+    test_case_number: usize,
+    input: &str,
+    output: &str,
+    verbose: bool,
+    mut case_trace: Option<CaseTrace>,
+  ) -> (CaseResult, Option<CaseTrace>) {
+    let mut steps = 0;
+
+    // Keep applying executions until no more to apply or we time out
+    let outcome = self.code.run(input, self.max_steps, |step| {
+      steps += 1;
+      if verbose {
+        println!(
+          "Rule (line {}): {}\n{}\n",
+          step.rule().line(),
+          step.rule(),
+          step.result()
+        );
+      }
+      if let Some(case_trace) = case_trace.as_mut() {
+        case_trace.write_step(step);
+      }
+    });
+
+    let (verdict, reason) = self.checker.verdict(input, &outcome, output);
+    if let Some(case_trace) = case_trace.as_mut() {
+      case_trace.end_case(&outcome, verdict);
+    }
+
+    let result = CaseResult {
+      number: test_case_number,
+      input: input.into(),
+      expected: output.into(),
+      outcome,
+      verdict,
+      reason,
+      steps,
+    };
+
+    if verbose {
+      result.print_details(self);
+    }
+
+    (result, case_trace)
+  }
+
+  /// Run the code on the input without printing anything and get the verdict
+  fn verdict(&self, input: &str, expected: &str) -> Verdict {
+    let outcome = self.code.run(input, self.max_steps, |_| {});
+    self.checker.verdict(input, &outcome, expected).0
+  }
+}
+
+#[allow(unused)]
+impl EdgeCase {
+  pub fn new(input: impl Into<String>, output: Option<String>) -> Self {
+    Self {
+      input: input.into(),
+      output,
+    }
+  }
+
+  pub fn input(&self) -> &str {
+    &self.input
+  }
+
+  pub fn output(&self) -> Option<&str> {
+    self.output.as_deref()
+  }
+}
+
+/// Result of running the code on a single test case
+#[derive(Debug, Clone)]
+struct CaseResult {
+  number: usize,
+  input: String,
+  expected: String,
+  outcome: Outcome,
+  verdict: Verdict,
+  /// Why the level rejected the output, if it gave a reason
+  reason: Option<String>,
+  /// Number of rules that ran
+  steps: usize,
+}
+
+impl CaseResult {
+  /// Print the final result of the test case after all of the steps
+  fn print_details(&self, runner: &TestRunner) {
+    match &self.outcome {
+      Outcome::Finished(output) => {
+        println!("Finished");
+        if self.verdict == Verdict::Passed && self.number == CUSTOM_INPUT {
+          println!("Output matches the expected output\n");
+        } else if self.verdict == Verdict::Passed {
+          println!("Passed test case {}\n", self.number);
+        } else if let Some(reason) = &self.reason {
+          println!("Error! Output was not accepted by the level");
+          println!("  Given:    {output}");
+          println!("  Reason:   {reason}");
+          println!("  Example:  {}\n", self.expected);
+        } else {
+          println!("Error! String does not match expected output");
+          println!("  Given:    {output}");
+          println!("  Expected: {}\n", self.expected);
+        }
+      },
+
+      // Print error if the execution timed out
+      Outcome::Timeout => {
+        println!(
+          "Error! Program exceeded maximum number of executions ({})",
+          runner.max_steps
+        );
+      },
+
+      // Print the rules involved if the program is stuck in a loop
+      Outcome::Loop(info) => {
+        println!(
+          "Error! Program is stuck in an infinite loop that repeats every {} executions",
+          info.period()
+        );
+        println!("Rules in the loop:");
+        let mut rule_indices = info.rule_indices().to_vec();
+        rule_indices.sort_unstable();
+        rule_indices.dedup();
+        for rule in rule_indices.into_iter().map(|i| runner.code.rule(i)) {
+          println!("  Line {}: {}", rule.line(), rule);
+        }
+      },
+    }
+  }
+}
+
+/// Get the failing test case with the shortest input
+fn smallest_failure(results: &[CaseResult]) -> Option<&CaseResult> {
+  results
+    .iter()
+    .filter(|r| r.verdict != Verdict::Passed)
+    .min_by_key(|r| (r.input.chars().count(), r.number))
+}
+
+///
+/// Print a table with the result of every test case
+///   Also shows the details for the failing test case with the smallest input
+///
+fn print_summary(results: &[CaseResult], runner: &TestRunner) {
+  println!("===== Summary: =====");
+  println!("{:>5} | {:<7} | Input", "Case", "Result");
+  println!("{:->5}-+-{:-<7}-+-{:-<SUMMARY_INPUT_WIDTH$}", "", "", "");
+
+  let smallest_failure = smallest_failure(results);
+
+  for result in results {
+    let mut input: String = result.input.chars().take(SUMMARY_INPUT_WIDTH).collect();
+    if result.input.chars().count() > SUMMARY_INPUT_WIDTH {
+      input.push_str("...");
+    }
+
+    let marker = match smallest_failure {
+      Some(smallest) if smallest.number == result.number => "  <-- smallest failing input",
+      _ => "",
+    };
+    println!("{:>5} | {:<7} | {input}{marker}", result.number, result.verdict.name());
+  }
+
+  let count = |verdict| results.iter().filter(|r| r.verdict == verdict).count();
+  println!(
+    "\nPassed {} / {} test cases ({} failed, {} timed out, {} infinite loops)\n",
+    count(Verdict::Passed),
+    results.len(),
+    count(Verdict::Failed),
+    count(Verdict::Timeout),
+    count(Verdict::Loop)
+  );
+
+  if let Some(smallest) = smallest_failure {
+    println!("===== Smallest failing test case {}: =====", smallest.number);
+    println!("  Input:  {}\n  Output: {}\n", smallest.input, smallest.expected);
+    smallest.print_details(runner);
   }
 }
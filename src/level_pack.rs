@@ -4,17 +4,27 @@ use rand_pcg::Pcg64;
 use rand_seeder::Seeder;
 use serde::Deserialize;
 use std::collections::{BTreeMap, HashMap};
-use std::fs::{self, File};
-use std::io::{self, BufReader, ErrorKind};
-use std::path::Path;
+use std::env;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
 
-use crate::level::Level;
+use crate::level::{Level, LevelSettings};
+use crate::pack_source::{self, PackSource};
 
 pub type LevelNumber = usize;
 
 pub const PACKS_FOLDER: &str = "packs";
 pub const PACK_JSON_FILE: &str = "pack.json";
 
+/// Folder for the program inside the user and system folders
+pub const APP_FOLDER: &str = "stringer-puzzles";
+/// Environment variable with more folders to search for level packs
+pub const PACKS_PATH_VAR: &str = "STRINGER_PACKS_PATH";
+/// Folder for level packs that are installed for every user
+#[cfg(unix)]
+const SYSTEM_PACKS_FOLDER: &str = "/usr/share/stringer-puzzles/packs";
+
 const CODE_LENGTH: usize = 6;
 static CODE_CHARS: &[char] = &[
   'B', 'C', 'D', 'F', 'G', 'H', 'J', 'K', 'L', 'M', 'N', 'P', 'Q', 'R', 'S', 'T', 'V', 'W', 'X', 'Y', 'Z', '1', '2',
@@ -31,6 +41,31 @@ fn generate_single_code<R: Rng + ?Sized>(rng: &mut R) -> String {
     .collect()
 }
 
+/// Folder for level packs that are installed for the current user
+pub fn user_packs_folder() -> Option<PathBuf> {
+  dirs::data_dir().map(|folder| folder.join(APP_FOLDER).join(PACKS_FOLDER))
+}
+
+///
+/// Get every folder to search for level packs, from lowest to highest priority
+///   The system folder comes first, followed by the user data and config folders,
+///   the `packs` folder in the current directory, the folders in the `STRINGER_PACKS_PATH`
+///   environment variable, and then any extra folders (e.g. from the command line)
+///
+pub fn pack_search_paths(extra_folders: &[PathBuf]) -> Vec<PathBuf> {
+  let mut paths = Vec::new();
+  #[cfg(unix)]
+  paths.push(PathBuf::from(SYSTEM_PACKS_FOLDER));
+  paths.extend(user_packs_folder());
+  paths.extend(dirs::config_dir().map(|folder| folder.join(APP_FOLDER).join(PACKS_FOLDER)));
+  paths.push(PathBuf::from(PACKS_FOLDER));
+  if let Some(var) = env::var_os(PACKS_PATH_VAR) {
+    paths.extend(env::split_paths(&var));
+  }
+  paths.extend(extra_folders.iter().cloned());
+  paths
+}
+
 /// Store all loaded level packs inside a single data structure
 #[derive(Debug, Clone, Default)]
 pub struct AllLevelPacks {
@@ -48,10 +83,12 @@ pub struct LevelPack {
   description: String,
   levels: Vec<Level>,
   win_message: Option<String>,
+  #[serde(flatten)]
+  settings: LevelSettings,
 
-  // Name of the parent folder
+  // Where to load the Lua files from
   #[serde(skip)]
-  parent_folder: String,
+  source: PackSource,
 
   // Internal fields for storing the level codes
   #[serde(skip)]
@@ -70,30 +107,61 @@ fn default_version() -> String {
 #[allow(unused)]
 impl LevelPack {
   ///
-  /// Load a level pack from a folder
+  /// Load a level pack from the pack file inside a folder
   ///   Returns an error if there are no levels inside the pack file
   ///
   pub fn from_file<P: AsRef<Path>>(json_pack_file: P) -> io::Result<Self> {
-    // Parse the level as a JSON file
-    let file = File::open(json_pack_file)?;
-    let reader = BufReader::new(file);
-    let mut me: Self = serde_json::from_reader(reader)?;
+    // Lua files are loaded from the full path, so the program can run from any folder
+    let json_pack_file = fs::canonicalize(json_pack_file)?;
+    let folder = json_pack_file.parent().map(Path::to_path_buf).unwrap_or_default();
+    let json = fs::read_to_string(&json_pack_file)?;
+    Self::from_json(&json, PackSource::Folder(folder))
+  }
+
+  ///
+  /// Load a level pack from a `.stringerpack` archive file
+  ///
+  pub fn from_archive(archive_file: &Path) -> io::Result<Self> {
+    Self::from_source(PackSource::from_archive(archive_file)?)
+  }
+
+  ///
+  /// Load a level pack from any source that has a pack file
+  ///
+  pub fn from_source(source: PackSource) -> io::Result<Self> {
+    let json = source.read(PACK_JSON_FILE)?;
+    Self::from_json(&json, source)
+  }
+
+  /// Parse the pack file and generate the level codes
+  fn from_json(json: &str, source: PackSource) -> io::Result<Self> {
+    let mut me: Self = serde_json::from_str(json)?;
+    me.source = source;
 
     // Make sure there is at least one level
-    if me.levels.len() == 0 {
+    if me.levels.is_empty() {
       Err(io::Error::new(
         ErrorKind::InvalidData,
-        format!("No levels provided in pack file"),
+        "No levels provided in pack file".to_string(),
       ))?;
     }
 
+    // Levels use the pack settings for anything they do not set themselves
+    let invalid_settings = |e: String| io::Error::new(ErrorKind::InvalidData, e);
+    me.settings.validate().map_err(invalid_settings)?;
+    for level in &mut me.levels {
+      let level_error = |e| invalid_settings(format!("level '{}': {e}", level.name()));
+      level.settings().validate().map_err(level_error)?;
+      level.inherit_settings(&me.settings);
+    }
+
     // Generate the level codes
     let mut rng: Pcg64 = Seeder::from(format!("{}-{}", me.id, me.version)).make_rng();
     let mut last_level_code = String::new();
 
     for index in 0..me.levels.len() {
       let mut code = generate_single_code(&mut rng);
-      while me.codes.get(&code).is_some() {
+      while me.codes.contains_key(&code) {
         code = generate_single_code(&mut rng);
       }
 
@@ -128,15 +196,25 @@ impl LevelPack {
   }
 
   pub fn win_message(&self) -> &str {
-    self
-      .win_message
-      .as_ref()
-      .map(String::as_str)
-      .unwrap_or(DEFAULT_WIN_MESSAGE)
+    self.win_message.as_deref().unwrap_or(DEFAULT_WIN_MESSAGE)
+  }
+
+  pub fn source(&self) -> &PackSource {
+    &self.source
   }
 
-  pub fn parent_folder(&self) -> &str {
-    &self.parent_folder
+  pub fn levels(&self) -> &[Level] {
+    &self.levels
+  }
+
+  /// Get the Lua files for the levels that do not exist in the pack
+  pub fn missing_lua_files(&self) -> Vec<&str> {
+    self
+      .levels
+      .iter()
+      .map(Level::lua_file)
+      .filter(|lua_file| self.source.read(lua_file).is_err())
+      .collect()
   }
 
   /// Get the code for the first level in the pack
@@ -157,6 +235,15 @@ impl LevelPack {
     self.next_level_code.get(code).map(String::as_str)
   }
 
+  /// Get the code for every level in order
+  pub fn level_codes(&self) -> Vec<&str> {
+    let mut codes = vec![self.starting_code.as_str()];
+    while let Some(code) = self.get_next_level_code(codes[codes.len() - 1]) {
+      codes.push(code);
+    }
+    codes
+  }
+
   /// Print out details about the level pack
   pub fn print(&self, pack_code: &str) {
     println!("Level Pack: {}", self.name);
@@ -171,63 +258,85 @@ impl LevelPack {
     let mut level_code = self.starting_code.as_str();
     for (level, level_number) in self.levels.iter().zip(1..) {
       println!("{} = Level {}: {}", level_code, level_number, level.name());
-      level_code = self.get_next_level_code(level_code).unwrap_or(&"");
+      level_code = self.get_next_level_code(level_code).unwrap_or("");
     }
   }
 }
 
 impl AllLevelPacks {
-  /// Load any level packs
-  ///   Returns an empty list of packs on errors
-  pub fn load() -> Self {
-    let result: io::Result<_> = (|| {
-      let mut level_packs = Vec::new();
-      for entry in fs::read_dir(PACKS_FOLDER)? {
-        let entry = entry?;
-
-        // Only directories are level packs
-        let mut path = entry.path();
-        if path.is_dir() {
-          // Make sure the path has a pack.json file
-          path.push(PACK_JSON_FILE);
-          if !Path::new(&path).exists() {
-            continue;
-          }
-
-          // A badly formed level pack is not an error, just a warning
-          let mut level_pack = match LevelPack::from_file(path) {
-            Ok(pack) => pack,
-            Err(e) => {
-              println!("Warning: failed to load level pack: {}", e);
-              continue;
-            },
-          };
-
-          // Also set the parent folder
-          level_pack.parent_folder = entry.file_name().into_string().expect("Invalid path string");
-          level_packs.push(level_pack);
-        }
+  ///
+  /// Load the level packs from every folder in the search paths
+  ///   Folders that do not exist are skipped. When more than one folder has a pack with the same ID,
+  ///   the pack from the later folder replaces the earlier one.
+  ///
+  pub fn load(search_paths: &[PathBuf]) -> Self {
+    let mut packs_by_id = HashMap::new();
+
+    // Built-in packs come first, so a pack folder with the same ID can replace them
+    for source in PackSource::built_in() {
+      match LevelPack::from_source(source) {
+        Ok(pack) => {
+          packs_by_id.insert(pack.id.clone(), pack);
+        },
+        Err(e) => println!("Warning: failed to load level pack: {}", e),
       }
+    }
 
-      Ok(level_packs)
-    })();
+    for folder in search_paths.iter().filter(|folder| folder.is_dir()) {
+      match Self::load_folder(folder) {
+        Ok(packs) => packs_by_id.extend(packs.into_iter().map(|pack| (pack.id.clone(), pack))),
+        Err(e) => println!("Warning: failed to load level packs from '{}': {}", folder.display(), e),
+      }
+    }
+
+    Self::from_packs(packs_by_id.into_values().collect())
+  }
+
+  ///
+  /// Load every level pack inside a single folder
+  ///   Packs are loaded in order by folder name, so a later pack with the same ID always wins
+  ///
+  fn load_folder(folder: &Path) -> io::Result<Vec<LevelPack>> {
+    let mut paths = fs::read_dir(folder)?
+      .map(|entry| entry.map(|entry| entry.path()))
+      .collect::<io::Result<Vec<_>>>()?;
+    paths.sort();
+
+    let mut level_packs = Vec::new();
+    for path in paths {
+      // Level packs are either directories with a pack.json file or archive files
+      let pack_file = path.join(PACK_JSON_FILE);
+      let level_pack = if pack_source::is_archive(&path) {
+        LevelPack::from_archive(&path)
+      } else if path.is_dir() && pack_file.exists() {
+        LevelPack::from_file(pack_file)
+      } else {
+        continue;
+      };
+
+      // A badly formed level pack is not an error, just a warning
+      match level_pack {
+        Ok(pack) => level_packs.push(pack),
+        Err(e) => println!("Warning: failed to load level pack '{}': {}", path.display(), e),
+      }
+    }
 
-    // Return an empty level pack on a file system error
-    let packs = match result {
-      Ok(packs) => packs,
-      Err(e) => {
-        println!("Failed to load level packs: {}", e);
-        return Self { ..Default::default() };
-      },
-    };
+    Ok(level_packs)
+  }
 
-    // Generate the level pack codes (for disambiguation if needed)
-    let mut rng: Pcg64 = Seeder::from(PACK_JSON_FILE).make_rng();
+  ///
+  /// Generate the level pack codes (for disambiguation if needed)
+  ///   Each code comes from the pack ID, so it does not change when other packs are installed or removed.
+  ///   If two packs get the same code, the packs are sorted and the later one keeps generating codes until it finds a free one
+  ///
+  pub fn from_packs(mut packs: Vec<LevelPack>) -> Self {
+    packs.sort_by(|a, b| (&a.id, &a.version, a.source.to_string()).cmp(&(&b.id, &b.version, b.source.to_string())));
     let mut level_packs = BTreeMap::new();
 
     for pack in packs {
+      let mut rng: Pcg64 = Seeder::from(pack.id.as_str()).make_rng();
       let mut code = generate_single_code(&mut rng);
-      while level_packs.get(&code).is_some() {
+      while level_packs.contains_key(&code) {
         code = generate_single_code(&mut rng);
       }
       level_packs.insert(code, pack);
@@ -245,6 +354,16 @@ impl AllLevelPacks {
     }
   }
 
+  /// Print every loaded pack along with its ID and where it was loaded from
+  pub fn print_pack_list(&self) {
+    println!("--- Level Packs: ---");
+    for (code, level_pack) in &self.level_packs {
+      println!("{} = {} (version {})", code, level_pack.name(), level_pack.version());
+      println!("  ID: {}", level_pack.id());
+      println!("  Source: {}\n", level_pack.source());
+    }
+  }
+
   /// Print the generated codes for every level pack
   pub fn print_level_codes(&self) {
     for (pack_code, level_pack) in &self.level_packs {
@@ -253,9 +372,9 @@ impl AllLevelPacks {
       let mut level_code = level_pack.starting_code.as_str();
       for (level, level_number) in level_pack.levels.iter().zip(1..) {
         println!("  {} = Level {}: {}", level_code, level_number, level.name());
-        level_code = level_pack.get_next_level_code(level_code).unwrap_or(&"");
+        level_code = level_pack.get_next_level_code(level_code).unwrap_or("");
       }
-      println!("");
+      println!();
     }
   }
 
@@ -264,6 +383,20 @@ impl AllLevelPacks {
     self.level_packs.get(code)
   }
 
+  /// Get a level pack and its code given the ID from the pack file
+  pub fn get_level_pack_by_id(&self, id: &str) -> Option<(&str, &LevelPack)> {
+    self
+      .level_packs
+      .iter()
+      .find(|(_, pack)| pack.id == id)
+      .map(|(code, pack)| (code.as_str(), pack))
+  }
+
+  /// Get every level pack along with its code
+  pub fn level_packs(&self) -> impl Iterator<Item = (&str, &LevelPack)> {
+    self.level_packs.iter().map(|(code, pack)| (code.as_str(), pack))
+  }
+
   /// Get a level(s) given the level code
   ///   This could return 0, 1, or more levels
   pub fn get_level(&self, level_code: &str, pack_code: Option<&str>) -> Vec<(&str, LevelNumber, &Level)> {
@@ -292,3 +425,54 @@ impl AllLevelPacks {
       .collect()
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn pack(id: &str, version: &str) -> LevelPack {
+    let json = serde_json::json!({
+      "id": id,
+      "version": version,
+      "name": format!("Pack {id}"),
+      "description": "Test level pack",
+      "levels": [{ "name": "Level", "description": "Test level", "luaFile": "level.lua" }],
+    });
+    serde_json::from_value(json).unwrap()
+  }
+
+  fn codes(all_packs: &AllLevelPacks) -> BTreeMap<(String, String), String> {
+    all_packs
+      .level_packs()
+      .map(|(code, pack)| ((pack.id().to_string(), pack.version().to_string()), code.to_string()))
+      .collect()
+  }
+
+  #[test]
+  fn pack_codes_do_not_depend_on_other_packs() {
+    let alone = codes(&AllLevelPacks::from_packs(vec![pack("first", "1.0.0")]));
+    let together = codes(&AllLevelPacks::from_packs(vec![
+      pack("second", "1.0.0"),
+      pack("first", "1.0.0"),
+      pack("third", "2.0.0"),
+    ]));
+
+    let key = ("first".to_string(), "1.0.0".to_string());
+    assert_eq!(alone[&key], together[&key]);
+  }
+
+  #[test]
+  fn pack_code_collisions_do_not_depend_on_order() {
+    let forward = codes(&AllLevelPacks::from_packs(vec![
+      pack("same", "1.0.0"),
+      pack("same", "2.0.0"),
+    ]));
+    let backward = codes(&AllLevelPacks::from_packs(vec![
+      pack("same", "2.0.0"),
+      pack("same", "1.0.0"),
+    ]));
+
+    assert_eq!(forward.len(), 2);
+    assert_eq!(forward, backward);
+  }
+}
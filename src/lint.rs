@@ -0,0 +1,238 @@
+use serde_json::Value;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use crate::level::Level;
+use crate::level_pack::{LevelPack, PACK_JSON_FILE};
+use crate::pack_source::{self, PackSource};
+use crate::script::LevelScript;
+
+// Fields that each object in the pack file can have, used to find typos
+const PACK_FIELDS: &[&str] = &["id", "name", "version", "description", "levels", "winMessage"];
+const LEVEL_FIELDS: &[&str] = &[
+  "name",
+  "description",
+  "luaFile",
+  "edgeCases",
+  "constraints",
+  "parLines",
+  "parSteps",
+];
+const SETTINGS_FIELDS: &[&str] = &["testCases", "examples", "maxSteps", "seed"];
+const CONSTRAINTS_FIELDS: &[&str] = &[
+  "maxRules",
+  "maxRuleLength",
+  "leftAlphabet",
+  "rightAlphabet",
+  "bannedCharacters",
+  "forbiddenRules",
+];
+const EDGE_CASE_FIELDS: &[&str] = &["input", "output"];
+
+/// Counts the problems found in a level pack while printing each one
+#[derive(Debug, Default)]
+struct Report {
+  errors: usize,
+  warnings: usize,
+}
+
+impl Report {
+  fn error(&mut self, message: impl Display) {
+    println!("  Error: {message}");
+    self.errors += 1;
+  }
+
+  fn warning(&mut self, message: impl Display) {
+    println!("  Warning: {message}");
+    self.warnings += 1;
+  }
+
+  /// Print the totals and see if the pack passed
+  fn finish(self) -> bool {
+    println!("Found {} errors and {} warnings", self.errors, self.warnings);
+    self.errors == 0
+  }
+}
+
+///
+/// Check a level pack for problems before publishing it
+///   The path can be a pack folder, a pack file, or a `.stringerpack` archive.
+///   Checks the pack file for unknown fields and duplicate level names, makes sure every Lua file exists,
+///   and runs each generator with several seeds to make sure it returns two strings and gives the same
+///   test cases every time it runs with the same seed. Returns true if there were no errors
+///
+pub fn lint_pack(path: &Path, seeds: usize) -> bool {
+  let mut report = Report::default();
+  let source = match pack_source(path) {
+    Ok(source) => source,
+    Err(e) => {
+      report.error(format!("cannot open level pack '{}': {e}", path.display()));
+      return report.finish();
+    },
+  };
+
+  println!("===== Pack file: {} =====", source);
+  let json = match source.read(PACK_JSON_FILE) {
+    Ok(json) => json,
+    Err(e) => {
+      report.error(format!("cannot read {PACK_JSON_FILE}: {e}"));
+      return report.finish();
+    },
+  };
+  if let Ok(value) = serde_json::from_str::<Value>(&json) {
+    check_pack_fields(&value, &mut report);
+  }
+
+  let pack = match LevelPack::from_source(source.clone()) {
+    Ok(pack) => pack,
+    Err(e) => {
+      report.error(format!("invalid {PACK_JSON_FILE}: {e}"));
+      return report.finish();
+    },
+  };
+  println!("  Name: {} (version {})", pack.name(), pack.version());
+  println!("  Levels: {}", pack.levels().len());
+
+  let mut names = HashSet::new();
+  for level in pack.levels() {
+    if !names.insert(level.name()) {
+      report.error(format!("level name '{}' is used more than once", level.name()));
+    }
+  }
+  println!();
+
+  for (level, level_number) in pack.levels().iter().zip(1..) {
+    println!("===== Level {}: {} =====", level_number, level.name());
+    match source.read(level.lua_file()) {
+      Ok(_) => lint_generator(level, &source, seeds, &mut report),
+      Err(e) if e.kind() == ErrorKind::NotFound => {
+        report.error(format!("Lua file '{}' does not exist", level.lua_file()))
+      },
+      Err(e) => report.error(format!("invalid Lua file: {e}")),
+    }
+    println!();
+  }
+
+  report.finish()
+}
+
+/// Get the files for the level pack from a folder, pack file, or archive
+fn pack_source(path: &Path) -> std::io::Result<PackSource> {
+  if pack_source::is_archive(path) {
+    return PackSource::from_archive(path);
+  }
+
+  let path = fs::canonicalize(path)?;
+  match path.file_name() {
+    Some(name) if path.is_file() && name == PACK_JSON_FILE => {
+      Ok(PackSource::Folder(path.parent().unwrap_or(&path).to_path_buf()))
+    },
+    _ => Ok(PackSource::Folder(path)),
+  }
+}
+
+///
+/// Run the level's generator with each seed, twice, and print statistics about the test cases
+///
+fn lint_generator(level: &Level, source: &PackSource, seeds: usize, report: &mut Report) {
+  let count = level.settings().test_cases();
+  let mut test_cases = Vec::new();
+
+  for seed in (0..seeds as u32).map(|i| level.settings().seed().wrapping_add(i)) {
+    let first = generate(level, source, seed, count);
+    let second = generate(level, source, seed, count);
+    let (first, second) = match (first, second) {
+      (Ok(first), Ok(second)) => (first, second),
+      (Err(e), _) | (_, Err(e)) => {
+        return report.error(format!("generateTestCase() failed with seed {seed}: {e}"));
+      },
+    };
+
+    if let Some(index) = first.iter().zip(&second).position(|(a, b)| a != b) {
+      return report.error(format!(
+        "generateTestCase() is not deterministic: test case {} with seed {seed} changed when generated again",
+        index + 1
+      ));
+    }
+    test_cases.extend(first);
+  }
+
+  let unique: HashSet<_> = test_cases.iter().map(|(input, _)| input).collect();
+  println!(
+    "  Generated {} test cases with {} seeds ({} unique inputs)",
+    test_cases.len(),
+    seeds,
+    unique.len()
+  );
+  print_lengths("Input length: ", test_cases.iter().map(|(input, _)| input));
+  print_lengths("Output length:", test_cases.iter().map(|(_, output)| output));
+
+  if test_cases.len() > 1 && unique.len() == 1 {
+    report.warning("every generated test case has the same input");
+  }
+}
+
+/// Load the script with the seed and generate the test cases
+fn generate(
+  level: &Level,
+  source: &PackSource,
+  seed: u32,
+  count: usize,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+  let script = LevelScript::load(level.lua_file(), source, seed)?;
+  (0..count).map(|_| script.generate_strict_test_case()).collect()
+}
+
+/// Print the smallest, average, and largest number of characters in the strings
+fn print_lengths<'a>(label: &str, strings: impl Iterator<Item = &'a String>) {
+  let lengths: Vec<_> = strings.map(|s| s.chars().count()).collect();
+  if lengths.is_empty() {
+    return;
+  }
+
+  let average = lengths.iter().sum::<usize>() as f64 / lengths.len() as f64;
+  println!(
+    "  {label} min {}, average {:.1}, max {}",
+    lengths.iter().min().unwrap_or(&0),
+    average,
+    lengths.iter().max().unwrap_or(&0)
+  );
+}
+
+///
+/// Warn about any fields in the pack file that the program does not use, which are usually typos
+///
+fn check_pack_fields(pack: &Value, report: &mut Report) {
+  check_fields(pack, &[PACK_FIELDS, SETTINGS_FIELDS], "pack", report);
+
+  let levels = pack.get("levels").and_then(Value::as_array).into_iter().flatten();
+  for (level, level_number) in levels.zip(1..) {
+    let location = format!("level {level_number}");
+    check_fields(level, &[LEVEL_FIELDS, SETTINGS_FIELDS], &location, report);
+
+    if let Some(constraints) = level.get("constraints") {
+      check_fields(
+        constraints,
+        &[CONSTRAINTS_FIELDS],
+        &format!("{location} constraints"),
+        report,
+      );
+    }
+    let edge_cases = level.get("edgeCases").and_then(Value::as_array).into_iter().flatten();
+    for edge_case in edge_cases {
+      check_fields(edge_case, &[EDGE_CASE_FIELDS], &format!("{location} edge case"), report);
+    }
+  }
+}
+
+/// Warn about every field in the object that is not one of the known fields
+fn check_fields(object: &Value, known: &[&[&str]], location: &str, report: &mut Report) {
+  let fields = object.as_object().into_iter().flat_map(|object| object.keys());
+  for field in fields.filter(|field| !known.iter().any(|known| known.contains(&field.as_str()))) {
+    report.warning(format!("unknown field '{field}' in the {location}"));
+  }
+}
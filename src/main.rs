@@ -1,11 +1,29 @@
+mod checker;
+mod constraints;
+mod debugger;
+mod engine;
+mod fuzz;
+mod install;
 mod level;
 mod level_pack;
+mod lint;
+mod pack_source;
+mod parser;
 mod program;
+mod progress;
+mod score;
+mod script;
+mod shrink;
+mod trace;
 
+use debugger::Debugger;
+use level::MAX_EXECUTIONS;
 use level_pack::AllLevelPacks;
 use program::Program;
-use std::path::PathBuf;
+use progress::Progress;
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
+use trace::{TraceFormat, TraceWriter};
 
 /// Fun string substitution puzzles
 #[derive(StructOpt)]
@@ -22,16 +40,98 @@ struct Opt {
   #[structopt(short = "p", long)]
   pack_code: Option<String>,
 
+  /// Step through the code file interactively instead of running all test cases
+  #[structopt(short, long)]
+  debug: bool,
+
+  /// Input string to run or debug, instead of using the test cases from the level
+  #[structopt(short, long)]
+  input: Option<String>,
+
+  /// Test case number from the level to debug
+  #[structopt(long, default_value = "1")]
+  test_case: usize,
+
+  /// Run every test case and print a summary instead of stopping at the first failure
+  #[structopt(short = "a", long = "all")]
+  run_all: bool,
+
+  /// Also run this many random test cases once all of the level's test cases pass
+  #[structopt(long)]
+  fuzz: Option<usize>,
+
+  /// Do not search for a smaller failing input when a test case fails
+  #[structopt(long)]
+  no_shrink: bool,
+
+  /// Write a machine-readable trace of every test case to the file
+  #[structopt(long, parse(from_os_str))]
+  trace: Option<PathBuf>,
+
+  /// Format for the trace file: json or ndjson
+  #[structopt(long, default_value = "ndjson")]
+  trace_format: TraceFormat,
+
+  /// Extra folder to search for level packs, which can be given more than once
+  #[structopt(long, parse(from_os_str))]
+  pack_path: Vec<PathBuf>,
+
+  /// Show the levels you have solved in every level pack
+  #[structopt(long)]
+  progress: bool,
+
+  /// Go to the first unsolved level in the level pack from -p, or the level pack you played last
+  #[structopt(long)]
+  resume: bool,
+
   /// List all of the level codes (cheat flag)
   #[structopt(long, hidden = true)]
   show_codes: bool,
+
+  #[structopt(subcommand)]
+  command: Option<Command>,
+}
+
+#[derive(StructOpt)]
+enum Command {
+  /// Manage the installed level packs
+  Pack(PackCommand),
+}
+
+#[derive(StructOpt)]
+enum PackCommand {
+  /// Install a level pack archive (.stringerpack file) for the current user
+  Install {
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+  },
+
+  /// Remove an installed level pack archive given the pack ID
+  Uninstall { id: String },
+
+  /// List every loaded level pack along with its ID and where it was loaded from
+  List,
+
+  /// Check a level pack folder or archive for problems before publishing it
+  Lint {
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+
+    /// Number of random seeds to run each level generator with
+    #[structopt(long, default_value = "10")]
+    seeds: usize,
+  },
 }
 
 fn main() {
   let opt: Opt = Opt::from_args();
 
   // Try to load the levels
-  let all_packs = AllLevelPacks::load();
+  let all_packs = AllLevelPacks::load(&level_pack::pack_search_paths(&opt.pack_path));
+
+  if let Some(Command::Pack(command)) = opt.command {
+    return run_pack_command(command, &all_packs);
+  }
 
   // Cheat option to show the codes
   if opt.show_codes {
@@ -44,7 +144,30 @@ fn main() {
     };
   }
 
-  let level_code = match opt.level_code {
+  // Show the saved progress
+  if opt.progress {
+    return match Progress::load() {
+      Ok(progress) => progress.print(&all_packs),
+      Err(e) => println!("Failed to load progress: {}", e),
+    };
+  }
+
+  // Debugging an input string does not need a level
+  if opt.debug && opt.level_code.is_none() && !opt.resume {
+    if let (Some(input), Some(code_file)) = (&opt.input, &opt.code_file) {
+      let program = match load_program(code_file) {
+        Some(program) => program,
+        None => return,
+      };
+      return Debugger::new(&program, input, None, MAX_EXECUTIONS).run();
+    }
+  }
+
+  let (level_code, pack_code) = match opt.level_code {
+    None if opt.resume => match resume_level(&all_packs, opt.pack_code.as_deref()) {
+      Some((level_code, pack_code)) => (level_code, Some(pack_code)),
+      None => return,
+    },
     None => match opt.pack_code {
       None => return all_packs.print_loaded_packs(),
       Some(code) => match all_packs.get_level_pack(&code) {
@@ -52,48 +175,70 @@ fn main() {
         Some(pack) => return pack.print(&code),
       },
     },
-    Some(code) => code,
+    Some(code) => (code, opt.pack_code),
   };
-  let code_file = opt.code_file;
 
   // Search for the level
-  let (pack_code, level_number, level) =
-    match all_packs.get_level(&level_code, opt.pack_code.as_ref().map(|s| s.as_str())) {
-      levels if levels.len() == 0 => {
-        println!("Error! Unknown level code '{level_code}'");
-        return;
-      },
-      levels if levels.len() > 1 => {
-        println!("Ambiguous level code '{level_code}'.");
-        println!("Please specify one of the following level packs:");
+  let (pack_code, level_number, level) = match all_packs.get_level(&level_code, pack_code.as_deref()) {
+    levels if levels.is_empty() => {
+      println!("Error! Unknown level code '{level_code}'");
+      return;
+    },
+    levels if levels.len() > 1 => {
+      println!("Ambiguous level code '{level_code}'.");
+      println!("Please specify one of the following level packs:");
 
-        for (code, _, _) in levels {
-          let pack = all_packs.get_level_pack(code).unwrap(); // Will not fail
-          println!("  {} = {}", code, pack.name());
-        }
+      for (code, _, _) in levels {
+        let pack = all_packs.get_level_pack(code).unwrap(); // Will not fail
+        println!("  {} = {}", code, pack.name());
+      }
 
-        return;
-      },
+      return;
+    },
 
-      levels => levels[0],
-    };
+    levels => levels[0],
+  };
   let level_pack = all_packs.get_level_pack(pack_code).unwrap(); // Will not fail
 
   // Show the level description if no code file provided
-  if code_file.is_none() {
-    level.print_level_details(level_number, &level_code, level_pack.parent_folder());
-    return;
-  }
+  let code_file = match opt.code_file {
+    Some(code_file) => code_file,
+    None => return level.print_level_details(level_number, &level_code, level_pack.source()),
+  };
 
   // Parse the code filProgram
-  let program = match Program::from_file(code_file.unwrap()) {
-    Ok(e) => e,
-    Err(e) => {
-      println!("Error loading code file: {}", e);
-      return;
-    },
+  let program = match load_program(&code_file) {
+    Some(program) => program,
+    None => return,
   };
 
+  // Step through a single test case in the debugger
+  if opt.debug {
+    let (input, expected) = match opt.input {
+      Some(input) => match level.solve(&input, level_pack.source()) {
+        Ok(output) => (input, Some(output)),
+        Err(e) => {
+          println!("Cannot get the expected output for input '{input}': {}\n", e);
+          (input, None)
+        },
+      },
+      None => match level.get_test_case(opt.test_case, level_pack.source()) {
+        Ok((input, output)) => (input, Some(output)),
+        Err(e) => return println!("Failed to load test case {}: {}", opt.test_case, e),
+      },
+    };
+
+    println!("Level {}: {}", level_number, level.name());
+    println!("  Code: {}\n", level_code);
+    let checker = match level.load_checker(level_pack.source()) {
+      Some(checker) => checker,
+      None => return,
+    };
+    return Debugger::new(&program, input, expected, level.settings().max_steps())
+      .with_checker(checker)
+      .run();
+  }
+
   // Always show a shortened level description
   println!("Level {}: {}", level_number, level.name());
   println!("  Code: {}\n", level_code);
@@ -102,12 +247,62 @@ fn main() {
   println!("----- Loaded Rules: -----");
   program.print_rules();
 
-  // Try the test cases on the level
-  if !level.validate_code(&program, level_pack.parent_folder()) {
+  // Reject the code before running anything if it breaks the level rules
+  if !level.check_constraints(&program) {
+    return;
+  }
+
+  // Run a single input string instead of the test cases
+  if let Some(input) = opt.input {
+    level.run_input(&program, level_pack.source(), &input, !opt.no_shrink);
     return;
   }
 
+  // Open the trace file before running any test cases
+  let mut trace = match opt.trace {
+    None => None,
+    Some(file) => match TraceWriter::create(&file, opt.trace_format) {
+      Ok(writer) => Some(writer),
+      Err(e) => return println!("Failed to create trace file '{}': {}", file.display(), e),
+    },
+  };
+
+  // Try the test cases on the level
+  let score = level.validate_code(
+    &program,
+    level_pack.source(),
+    opt.run_all,
+    !opt.no_shrink,
+    trace.as_mut(),
+  );
+  if let Some(Err(e)) = trace.map(TraceWriter::finish) {
+    println!("Warning: failed to write trace file: {}", e);
+  }
+  let score = match score {
+    Some(score) => score,
+    None => return,
+  };
+
+  // Try some random test cases as well
+  if let Some(count) = opt.fuzz {
+    if !level.fuzz_code(&program, level_pack.source(), count, !opt.no_shrink) {
+      return;
+    }
+  }
+
   println!("Success! All test cases passed!\n");
+  score.print(level);
+
+  // Remember the solution for the next time
+  let saved = Progress::load().and_then(|mut progress| {
+    let best = progress.record(level_pack, &level_code, level, &score, &code_file)?;
+    progress.save().map(|_| best)
+  });
+  match saved {
+    Ok(true) => println!("New best solution saved to your progress\n"),
+    Ok(false) => {},
+    Err(e) => println!("Warning: failed to save progress: {}\n", e),
+  }
 
   // Show the next level code
   match level_pack.get_next_level_code(&level_code) {
@@ -120,3 +315,89 @@ fn main() {
     },
   }
 }
+
+///
+/// Install, remove, or list the level packs
+///
+fn run_pack_command(command: PackCommand, all_packs: &AllLevelPacks) {
+  match command {
+    PackCommand::Install { file } => match install::install_pack(&file) {
+      Ok((pack, installed)) => println!(
+        "Installed level pack '{}' (version {}) to {}",
+        pack.name(),
+        pack.version(),
+        installed.display()
+      ),
+      Err(e) => println!("Failed to install level pack '{}': {}", file.display(), e),
+    },
+
+    PackCommand::Uninstall { id } => match install::uninstall_pack(&id) {
+      Ok(removed) if removed.is_empty() => println!("No installed level pack archive with the ID '{}'", id),
+      Ok(removed) => {
+        for file in removed {
+          println!("Removed {}", file.display());
+        }
+      },
+      Err(e) => println!("Failed to uninstall level pack '{}': {}", id, e),
+    },
+
+    PackCommand::List => all_packs.print_pack_list(),
+
+    // Fail the command when there are errors, so it can be used in scripts before publishing
+    PackCommand::Lint { path, seeds } => {
+      if !lint::lint_pack(&path, seeds) {
+        std::process::exit(1);
+      }
+    },
+  }
+}
+
+///
+/// Find the first unsolved level in the level pack from `-p`, or the level pack that was played last
+///   Returns the level code and pack code, or None after printing why there is no level to resume
+///
+fn resume_level(all_packs: &AllLevelPacks, pack_code: Option<&str>) -> Option<(String, String)> {
+  let progress = match Progress::load() {
+    Ok(progress) => progress,
+    Err(e) => {
+      println!("Failed to load progress: {}", e);
+      return None;
+    },
+  };
+
+  let (pack_code, pack) = match pack_code {
+    Some(code) => match all_packs.get_level_pack(code) {
+      Some(pack) => (code, pack),
+      None => {
+        println!("Unknown level pack ID '{}'", code);
+        return None;
+      },
+    },
+    None => match progress.last_pack().and_then(|id| all_packs.get_level_pack_by_id(id)) {
+      Some(found) => found,
+      None => {
+        println!("No saved progress to resume, pick a level pack with -p <pack-code>");
+        return None;
+      },
+    },
+  };
+
+  match progress.next_unsolved(pack) {
+    Some((_, level_code)) => Some((level_code.into(), pack_code.into())),
+    None => {
+      println!("You already solved every level in '{}'!", pack.name());
+      None
+    },
+  }
+}
+
+/// Parse the code file, printing any errors
+fn load_program(code_file: &Path) -> Option<Program> {
+  match Program::from_file(code_file) {
+    Ok(program) => Some(program),
+    Err(e) => {
+      println!("Error loading code file:\n\n{}", e);
+      None
+    },
+  }
+}
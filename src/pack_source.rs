@@ -0,0 +1,216 @@
+use include_dir::{include_dir, Dir};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::level_pack::PACK_JSON_FILE;
+
+/// File extension for level pack archives
+pub const ARCHIVE_EXTENSION: &str = "stringerpack";
+/// Maximum total size of the files inside a level pack archive, to avoid running out of memory
+const MAX_ARCHIVE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Level packs that are compiled into the program, so they are always available
+static BUILT_IN_PACKS: &[(&str, Dir)] = &[
+  (
+    "default-levels",
+    include_dir!("$CARGO_MANIFEST_DIR/packs/default-levels"),
+  ),
+  (
+    "extreme-levels",
+    include_dir!("$CARGO_MANIFEST_DIR/packs/extreme-levels"),
+  ),
+];
+
+///
+/// Where the files for a level pack come from
+///   Packs are usually folders on disk, but they can also be a set of files kept in memory,
+///   such as the packs that are built into the program
+///
+#[derive(Debug, Clone)]
+pub enum PackSource {
+  /// Full path to a pack folder
+  Folder(PathBuf),
+  /// Text of every file in the pack, using the path inside the pack with `/` separators as the key
+  Files {
+    name: String,
+    files: Arc<BTreeMap<String, String>>,
+  },
+}
+
+#[allow(unused)]
+impl PackSource {
+  ///
+  /// Get the source for every level pack that is built into the program
+  ///
+  pub fn built_in() -> Vec<Self> {
+    BUILT_IN_PACKS
+      .iter()
+      .map(|(folder_name, dir)| {
+        let mut files = BTreeMap::new();
+        add_dir_files(dir, &mut files);
+        PackSource::Files {
+          name: format!("built-in pack '{folder_name}'"),
+          files: Arc::new(files),
+        }
+      })
+      .collect()
+  }
+
+  ///
+  /// Load a level pack archive, which is a zip file with the `.stringerpack` extension
+  ///   The pack file can be at the top of the archive or inside a single folder,
+  ///   in which case every path is relative to that folder
+  ///
+  pub fn from_archive(archive_file: &Path) -> io::Result<Self> {
+    let mut archive = zip::ZipArchive::new(File::open(archive_file)?)?;
+    let mut files = BTreeMap::new();
+    let mut total_bytes = 0;
+
+    for index in 0..archive.len() {
+      let mut file = archive.by_index(index)?;
+      let path = match file.enclosed_name() {
+        Some(path) if file.is_file() && !is_hidden(path) => relative_path(path),
+        _ => continue,
+      };
+
+      // The size in the archive header cannot be trusted, so limit the actual bytes read
+      let mut bytes = Vec::new();
+      file
+        .by_ref()
+        .take(MAX_ARCHIVE_BYTES - total_bytes + 1)
+        .read_to_end(&mut bytes)?;
+      total_bytes += bytes.len() as u64;
+      if total_bytes > MAX_ARCHIVE_BYTES {
+        return Err(io::Error::new(
+          ErrorKind::InvalidData,
+          format!("archive is larger than {} MB", MAX_ARCHIVE_BYTES / 1024 / 1024),
+        ));
+      }
+
+      // Packs only read text files, so skip anything else such as images
+      if let Ok(text) = String::from_utf8(bytes) {
+        files.insert(path, text);
+      }
+    }
+
+    // Archives made by zipping the pack folder have every file inside that folder
+    if !files.contains_key(PACK_JSON_FILE) {
+      let first_folder = files.keys().next().and_then(|path| path.split_once('/'));
+      let prefix = first_folder.map(|(folder, _)| format!("{folder}/"));
+      if let Some(prefix) = prefix.filter(|prefix| files.keys().all(|path| path.starts_with(prefix))) {
+        files = files
+          .into_iter()
+          .map(|(path, text)| (path[prefix.len()..].to_string(), text))
+          .collect();
+      }
+    }
+
+    Ok(PackSource::Files {
+      name: archive_file.display().to_string(),
+      files: Arc::new(files),
+    })
+  }
+
+  ///
+  /// Read a text file from the pack, given the path inside the pack
+  ///   Fails for absolute paths and paths with `..`, so files outside of the pack cannot be read
+  ///
+  pub fn read(&self, file: &str) -> io::Result<String> {
+    let path = pack_path(file)?;
+    match self {
+      PackSource::Folder(folder) => fs::read_to_string(folder.join(path)),
+      PackSource::Files { files, .. } => files.get(&path).cloned().ok_or_else(|| {
+        io::Error::new(
+          ErrorKind::NotFound,
+          format!("file '{file}' does not exist in the level pack"),
+        )
+      }),
+    }
+  }
+}
+
+/// Pack without any files, until the real source is known
+impl Default for PackSource {
+  fn default() -> Self {
+    PackSource::Files {
+      name: "empty pack".into(),
+      files: Arc::default(),
+    }
+  }
+}
+
+impl fmt::Display for PackSource {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      PackSource::Folder(folder) => write!(f, "{}", folder.display()),
+      PackSource::Files { name, .. } => write!(f, "{name}"),
+    }
+  }
+}
+
+/// See if the path is a level pack archive file
+pub fn is_archive(path: &Path) -> bool {
+  path.is_file() && path.extension().is_some_and(|extension| extension == ARCHIVE_EXTENSION)
+}
+
+/// See if the archive entry is a dotfile or inside the `__MACOSX` folder, which zip tools add to archives
+fn is_hidden(path: &Path) -> bool {
+  path.components().any(|component| match component {
+    Component::Normal(part) => {
+      let part = part.to_string_lossy();
+      part.starts_with('.') || part == "__MACOSX"
+    },
+    _ => false,
+  })
+}
+
+/// Add every text file in the built-in folder, using the path relative to the folder as the key
+fn add_dir_files(dir: &Dir, files: &mut BTreeMap<String, String>) {
+  for file in dir.files() {
+    if let Some(text) = file.contents_utf8() {
+      files.insert(relative_path(file.path()), text.into());
+    }
+  }
+  for sub_dir in dir.dirs() {
+    add_dir_files(sub_dir, files);
+  }
+}
+
+///
+/// Check that a path from the pack stays inside the pack and get it with `/` separators
+///   Only plain folder and file names are allowed, along with `.` which is skipped
+///
+fn pack_path(file: &str) -> io::Result<String> {
+  let file = file.replace('\\', "/");
+  let mut parts = Vec::new();
+  for component in Path::new(&file).components() {
+    match component {
+      Component::Normal(part) => parts.push(part.to_string_lossy()),
+      Component::CurDir => {},
+      Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+        return Err(io::Error::new(
+          ErrorKind::InvalidInput,
+          format!("path '{file}' must be relative and stay inside the level pack"),
+        ))
+      },
+    }
+  }
+
+  Ok(parts.join("/"))
+}
+
+/// Get the path with `/` separators, which is how files are looked up inside a pack
+fn relative_path(path: &Path) -> String {
+  path
+    .components()
+    .filter_map(|component| match component {
+      Component::Normal(part) => part.to_str(),
+      _ => None,
+    })
+    .collect::<Vec<_>>()
+    .join("/")
+}
@@ -0,0 +1,392 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+use crate::program::{Anchor, Rule, Target};
+
+/// Characters that must be escaped or quoted to appear literally in a rule
+const SPECIAL_CHARS: &[char] = &['\\', '"', '=', ':', '#'];
+
+/// Single character or operator inside a line of code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+  /// Character that is part of the left or right side of a rule
+  ///   Literal characters came from an escape sequence or quotes,
+  ///   so they are never trimmed or treated as a keyword
+  Char { c: char, literal: bool },
+  /// Unescaped ':' character
+  Colon,
+  /// Unescaped '=' character
+  Equals,
+}
+
+/// Token along with the columns it came from in the line
+#[derive(Debug, Clone)]
+struct Token {
+  kind: TokenKind,
+  columns: Range<usize>,
+}
+
+/// Error found when parsing a line of code
+#[derive(Debug, Clone)]
+pub struct ParseError {
+  kind: ParseErrorKind,
+  line: usize,
+  columns: Range<usize>,
+  source: String,
+}
+
+/// All of the different problems that can happen when parsing a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+  /// Backslash followed by a character that cannot be escaped
+  UnknownEscape(char),
+  /// Backslash at the very end of the line
+  TrailingBackslash,
+  /// Quotes that never get closed
+  UnterminatedQuote,
+  /// Non-empty line that is not a rule
+  MissingEquals,
+  /// More than one unescaped '=' in the line
+  MultipleEquals,
+  /// Unescaped ':' that is not right before the '='
+  MisplacedColon,
+  /// Repeat count in ':N=' that is zero or too large
+  InvalidRepeatCount,
+}
+
+#[allow(unused)]
+impl ParseError {
+  fn new(kind: ParseErrorKind, line: usize, columns: Range<usize>, source: &str) -> Self {
+    Self {
+      kind,
+      line,
+      columns,
+      source: source.into(),
+    }
+  }
+
+  pub fn kind(&self) -> &ParseErrorKind {
+    &self.kind
+  }
+
+  pub fn line(&self) -> usize {
+    self.line
+  }
+
+  /// Range of character columns with the error, starting at column 1
+  pub fn columns(&self) -> Range<usize> {
+    (self.columns.start + 1)..(self.columns.end + 1)
+  }
+
+  pub fn hint(&self) -> Option<&'static str> {
+    self.kind.hint()
+  }
+}
+
+impl ParseErrorKind {
+  /// Suggestion for how to fix the error
+  pub fn hint(&self) -> Option<&'static str> {
+    match self {
+      ParseErrorKind::UnknownEscape(_) => Some(r#"valid escapes are \\ \" \= \: \# \( \t \n and "\ " (space)"#),
+      ParseErrorKind::TrailingBackslash => Some(r"use \\ to write a literal backslash"),
+      ParseErrorKind::UnterminatedQuote => Some(r#"add a closing '"', or use \" to write a literal quote"#),
+      ParseErrorKind::MissingEquals => Some("rules are written as <left>=<right>, and comments start with '#'"),
+      ParseErrorKind::MultipleEquals => Some(r#"use \= or quotes to write a literal '='"#),
+      ParseErrorKind::MisplacedColon => {
+        Some(r#"limited rules are written as ':=' or ':N=', use \: or quotes to write a literal ':'"#)
+      },
+      ParseErrorKind::InvalidRepeatCount => Some("the repeat count must be a number of at least 1"),
+    }
+  }
+}
+
+impl fmt::Display for ParseErrorKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ParseErrorKind::UnknownEscape(c) => write!(f, "unknown escape sequence '\\{c}'"),
+      ParseErrorKind::TrailingBackslash => write!(f, "backslash at end of line"),
+      ParseErrorKind::UnterminatedQuote => write!(f, "unterminated quote"),
+      ParseErrorKind::MissingEquals => write!(f, "line is not a rule"),
+      ParseErrorKind::MultipleEquals => write!(f, "rule has more than one '='"),
+      ParseErrorKind::MisplacedColon => write!(f, "':' must come directly before '=' or a repeat count"),
+      ParseErrorKind::InvalidRepeatCount => write!(f, "invalid repeat count"),
+    }
+  }
+}
+
+///
+/// Show the error with the line of code and carets underneath the problem
+///
+/// ```text
+/// error: rule has more than one '=' (line 3, column 6)
+///   |
+/// 3 | 1+1=2=3
+///   |      ^
+///   = hint: use \= or quotes to write a literal '='
+/// ```
+///
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let gutter = " ".repeat(self.line.to_string().len());
+    let source: String = self.source.chars().map(|c| if c == '\t' { ' ' } else { c }).collect();
+    let underline = format!(
+      "{}{}",
+      " ".repeat(self.columns.start),
+      "^".repeat((self.columns.end - self.columns.start).max(1))
+    );
+
+    writeln!(
+      f,
+      "error: {} (line {}, column {})",
+      self.kind,
+      self.line,
+      self.columns.start + 1
+    )?;
+    writeln!(f, "{gutter} |")?;
+    writeln!(f, "{} | {source}", self.line)?;
+    write!(f, "{gutter} | {underline}")?;
+    if let Some(hint) = self.hint() {
+      write!(f, "\n{gutter} = hint: {hint}")?;
+    }
+
+    Ok(())
+  }
+}
+
+impl Error for ParseError {}
+
+///
+/// Try to parse a single line in the program
+///   Returns None if the line is blank or only has a comment
+///
+pub fn parse_line(line: &str, line_number: usize) -> Result<Option<Rule>, ParseError> {
+  let error = |kind, columns| ParseError::new(kind, line_number, columns, line);
+
+  let tokens = tokenize(line).map_err(|(kind, columns)| error(kind, columns))?;
+  let content = trim(&tokens);
+  if content.is_empty() {
+    return Ok(None);
+  }
+
+  // Search for an '=' equal sign
+  //  Any other non-empty line is not a valid rule
+  let equal_sign = match tokens.iter().position(|t| t.kind == TokenKind::Equals) {
+    Some(index) => index,
+    None => {
+      let columns = content[0].columns.start..content[content.len() - 1].columns.end;
+      return Err(error(ParseErrorKind::MissingEquals, columns));
+    },
+  };
+
+  // Make sure there is only one equals sign
+  //  Otherwise, we have a badly-formed substitution
+  if let Some(extra) = tokens[(equal_sign + 1)..].iter().find(|t| t.kind == TokenKind::Equals) {
+    return Err(error(ParseErrorKind::MultipleEquals, extra.columns.clone()));
+  }
+
+  // Check for the prescence of the once ':' or repeat ':N' operator
+  //  Make sure there is only one operation right before '=' sign
+  let limit_colon = tokens[..equal_sign]
+    .iter()
+    .rposition(|t| t.kind == TokenKind::Colon)
+    .filter(|colon| trim(&tokens[(colon + 1)..equal_sign]).iter().all(is_digit));
+  if let Some((_, colon)) = tokens
+    .iter()
+    .enumerate()
+    .find(|(i, t)| t.kind == TokenKind::Colon && Some(*i) != limit_colon)
+  {
+    return Err(error(ParseErrorKind::MisplacedColon, colon.columns.clone()));
+  }
+
+  // Parse the repeat count, where no number is the same as ':='
+  let limit = match limit_colon {
+    None => None,
+    Some(colon) => match trim(&tokens[(colon + 1)..equal_sign]) {
+      [] => Some(1),
+      digits => match to_string(digits).parse::<usize>() {
+        Ok(n) if n > 0 => Some(n),
+        _ => {
+          let columns = digits[0].columns.start..digits[digits.len() - 1].columns.end;
+          return Err(error(ParseErrorKind::InvalidRepeatCount, columns));
+        },
+      },
+    },
+  };
+
+  // Extract the left and right sides of the rule
+  let left_side = &tokens[0..limit_colon.unwrap_or(equal_sign)];
+  let right_side = &tokens[(equal_sign + 1)..];
+
+  // Strip off any (start), (end), or (return) keywords
+  let (anchor, left_side) = split_keyword(trim(left_side), &[Anchor::Start, Anchor::End], Anchor::keyword);
+  let (target, right_side) = split_keyword(
+    trim(right_side),
+    &[Target::Start, Target::End, Target::Return],
+    Target::keyword,
+  );
+
+  Ok(Some(
+    Rule::new(to_string(trim(left_side)), to_string(trim(right_side)), limit)
+      .with_anchor(anchor.unwrap_or(Anchor::Anywhere))
+      .with_target(target.unwrap_or(Target::Replace))
+      .with_line(line_number),
+  ))
+}
+
+///
+/// Convert the line into a list of tokens, handling any escapes or quotes
+///   Everything after an unescaped '#' is a comment and gets ignored
+///
+fn tokenize(line: &str) -> Result<Vec<Token>, (ParseErrorKind, Range<usize>)> {
+  let line_length = line.chars().count();
+  let mut tokens = Vec::new();
+  let mut chars = line.chars().enumerate().peekable();
+
+  while let Some((column, c)) = chars.next() {
+    let kind = match c {
+      '\\' => match chars.next() {
+        None => return Err((ParseErrorKind::TrailingBackslash, column..(column + 1))),
+        Some((_, escaped)) => TokenKind::Char {
+          c: unescape(escaped, false).ok_or((ParseErrorKind::UnknownEscape(escaped), column..(column + 2)))?,
+          literal: true,
+        },
+      },
+
+      '"' => {
+        // Everything inside the quotes is literal
+        loop {
+          let (start, c) = chars
+            .next()
+            .ok_or((ParseErrorKind::UnterminatedQuote, column..line_length))?;
+
+          let c = match c {
+            '"' => break,
+            '\\' => match chars.next() {
+              None => return Err((ParseErrorKind::UnterminatedQuote, column..line_length)),
+              Some((_, escaped)) => {
+                unescape(escaped, true).ok_or((ParseErrorKind::UnknownEscape(escaped), start..(start + 2)))?
+              },
+            },
+            c => c,
+          };
+
+          let end = chars.peek().map(|(i, _)| *i).unwrap_or(line_length);
+          tokens.push(Token {
+            kind: TokenKind::Char { c, literal: true },
+            columns: start..end,
+          });
+        }
+        continue;
+      },
+
+      '#' => break,
+      ':' => TokenKind::Colon,
+      '=' => TokenKind::Equals,
+      c => TokenKind::Char { c, literal: false },
+    };
+
+    let end = chars.peek().map(|(i, _)| *i).unwrap_or(line_length);
+    tokens.push(Token {
+      kind,
+      columns: column..end,
+    });
+  }
+
+  Ok(tokens)
+}
+
+/// Get the character for an escape sequence
+fn unescape(c: char, quoted: bool) -> Option<char> {
+  match c {
+    't' => Some('\t'),
+    'n' => Some('\n'),
+    '\\' | '"' => Some(c),
+    '=' | ':' | '#' | '(' | ' ' if !quoted => Some(c),
+    _ => None,
+  }
+}
+
+/// Test if the token is an unescaped digit
+fn is_digit(token: &Token) -> bool {
+  matches!(token.kind, TokenKind::Char { c, literal: false } if c.is_ascii_digit())
+}
+
+/// Remove any unescaped whitespace from the start and end of the side
+fn trim(mut tokens: &[Token]) -> &[Token] {
+  let is_whitespace = |t: &Token| matches!(t.kind, TokenKind::Char { c, literal: false } if c.is_whitespace());
+
+  while tokens.first().is_some_and(is_whitespace) {
+    tokens = &tokens[1..];
+  }
+  while tokens.last().is_some_and(is_whitespace) {
+    tokens = &tokens[..(tokens.len() - 1)];
+  }
+
+  tokens
+}
+
+/// Split an unescaped keyword from the start of a side
+fn split_keyword<'a, T: Copy>(
+  tokens: &'a [Token],
+  options: &[T],
+  keyword: fn(&T) -> &'static str,
+) -> (Option<T>, &'a [Token]) {
+  for option in options {
+    let keyword = keyword(option);
+    let is_match = tokens.len() >= keyword.len()
+      && keyword
+        .chars()
+        .zip(tokens)
+        .all(|(k, t)| t.kind == TokenKind::Char { c: k, literal: false });
+
+    if is_match {
+      return (Some(*option), &tokens[keyword.chars().count()..]);
+    }
+  }
+
+  (None, tokens)
+}
+
+/// Convert the list of tokens back into a string
+fn to_string(tokens: &[Token]) -> String {
+  tokens
+    .iter()
+    .filter_map(|t| match t.kind {
+      TokenKind::Char { c, .. } => Some(c),
+      _ => None,
+    })
+    .collect()
+}
+
+///
+/// Format one side of a rule so it parses back to the same string
+///   Sides with special characters are wrapped in quotes
+///
+pub fn escape_side(side: &str) -> String {
+  let needs_quotes = side.contains(SPECIAL_CHARS)
+    || side.contains(|c: char| c.is_control())
+    || side.starts_with(char::is_whitespace)
+    || side.ends_with(char::is_whitespace)
+    || side.starts_with('(');
+
+  if !needs_quotes {
+    return side.into();
+  }
+
+  let mut result = String::from("\"");
+  for c in side.chars() {
+    match c {
+      '\\' | '"' => {
+        result.push('\\');
+        result.push(c);
+      },
+      '\t' => result.push_str("\\t"),
+      '\n' => result.push_str("\\n"),
+      c => result.push(c),
+    }
+  }
+  result.push('"');
+
+  result
+}
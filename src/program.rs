@@ -1,8 +1,11 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::error::Error;
 use std::fmt;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, ErrorKind};
 use std::path::Path;
+use std::{fs, io};
+
+use crate::engine::{Engine, MatchCache};
+use crate::parser::{self, ParseError};
 
 /// A program is a set of rules to run on the given input string
 #[derive(Debug, Clone)]
@@ -10,28 +13,131 @@ pub struct Program {
   rules: Vec<Rule>,
 }
 
-/// Data type for the state of the program when executing
+/// Details about a single rule that was applied to the string
 #[derive(Debug, Clone)]
-pub struct ProgramState(HashSet<usize>);
+pub struct Step<'a> {
+  rule: &'a Rule,
+  rule_index: usize,
+  offset: usize,
+  result: String,
+}
+
+/// Final result of running a program on an input string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+  /// No more rules matched, or a rule returned, with the given output
+  Finished(String),
+  /// Program did not stop before the maximum number of executions
+  Timeout,
+  /// Program returned to the same string and state it had before
+  Loop(LoopInfo),
+}
+
+/// Details about an infinite loop found when running a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopInfo {
+  period: usize,
+  rule_indices: Vec<usize>,
+}
+
+/// Error when loading a program from a file
+#[derive(Debug)]
+pub enum LoadError {
+  Io(io::Error),
+  Parse(Vec<ParseError>),
+}
+
+/// Data type for the state of the program when executing
+///   Counts how many times each limited rule has been applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramState(HashMap<usize, usize>);
 
 /// Single rule to handle substitution
 #[derive(Debug, Clone)]
 pub struct Rule {
   left: String,
   right: String,
-  once: bool,
+  limit: Option<usize>,
+  anchor: Anchor,
+  target: Target,
+  line: usize,
+}
+
+/// Where the left side of a rule is allowed to match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+  /// Match the first instance anywhere in the string
+  Anywhere,
+  /// `(start)` - Only match at the start of the string
+  Start,
+  /// `(end)` - Only match at the end of the string
+  End,
+}
+
+/// What to do with the right side of a rule after the left side matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+  /// Replace the matched text in place
+  Replace,
+  /// `(start)` - Remove the matched text and prepend to the string
+  Start,
+  /// `(end)` - Remove the matched text and append to the string
+  End,
+  /// `(return)` - Stop the program with the right side as the result
+  Return,
+}
+
+impl Anchor {
+  /// Keyword used when writing the anchor in a program
+  pub fn keyword(&self) -> &'static str {
+    match self {
+      Anchor::Anywhere => "",
+      Anchor::Start => "(start)",
+      Anchor::End => "(end)",
+    }
+  }
+}
+
+impl Target {
+  /// Keyword used when writing the target in a program
+  pub fn keyword(&self) -> &'static str {
+    match self {
+      Target::Replace => "",
+      Target::Start => "(start)",
+      Target::End => "(end)",
+      Target::Return => "(return)",
+    }
+  }
 }
 
 #[allow(unused)]
 impl Rule {
-  pub fn new(left: impl Into<String>, right: impl Into<String>, once: bool) -> Self {
+  pub fn new(left: impl Into<String>, right: impl Into<String>, limit: Option<usize>) -> Self {
     Rule {
       left: left.into(),
       right: right.into(),
-      once,
+      limit,
+      anchor: Anchor::Anywhere,
+      target: Target::Replace,
+      line: 0,
     }
   }
 
+  pub fn with_anchor(mut self, anchor: Anchor) -> Self {
+    self.anchor = anchor;
+    self
+  }
+
+  pub fn with_target(mut self, target: Target) -> Self {
+    self.target = target;
+    self
+  }
+
+  pub fn with_line(mut self, line: usize) -> Self {
+    self.line = line;
+    self
+  }
+
   pub fn left(&self) -> &str {
     &self.left
   }
@@ -41,85 +147,214 @@ impl Rule {
   }
 
   pub fn once(&self) -> bool {
-    self.once
+    self.limit == Some(1)
+  }
+
+  /// Maximum number of times the rule can be applied per test case, or None if unlimited
+  pub fn limit(&self) -> Option<usize> {
+    self.limit
+  }
+
+  pub fn anchor(&self) -> Anchor {
+    self.anchor
+  }
+
+  pub fn target(&self) -> Target {
+    self.target
+  }
+
+  /// Line number in the source file, or 0 if the rule was not loaded from a file
+  pub fn line(&self) -> usize {
+    self.line
+  }
+
+  /// Returns true if the program stops after this rule is applied
+  pub fn halts(&self) -> bool {
+    self.target == Target::Return
+  }
+
+  /// Find the byte offset where the left side matches, or None if it does not match
+  pub fn find(&self, input: &str) -> Option<usize> {
+    match self.anchor {
+      Anchor::Anywhere => input.find(&self.left),
+      Anchor::Start => input.starts_with(&self.left).then_some(0),
+      Anchor::End => input.ends_with(&self.left).then(|| input.len() - self.left.len()),
+    }
+  }
+
+  /// Apply the rule to a match found at the given byte offset
+  pub fn apply(&self, input: &str, offset: usize) -> String {
+    let before = &input[..offset];
+    let after = &input[(offset + self.left.len())..];
+
+    match self.target {
+      Target::Replace => format!("{before}{}{after}", self.right),
+      Target::Start => format!("{}{before}{after}", self.right),
+      Target::End => format!("{before}{after}{}", self.right),
+      Target::Return => self.right.clone(),
+    }
   }
 }
 
 impl fmt::Display for Rule {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    if self.once {
-      write!(f, "{}:={}", self.left, self.right)
-    } else {
-      write!(f, "{}={}", self.left, self.right)
+    let operator = match self.limit {
+      None => "=".to_string(),
+      Some(1) => ":=".to_string(),
+      Some(n) => format!(":{n}="),
+    };
+    write!(
+      f,
+      "{}{}{operator}{}{}",
+      self.anchor.keyword(),
+      parser::escape_side(&self.left),
+      self.target.keyword(),
+      parser::escape_side(&self.right)
+    )
+  }
+}
+
+#[allow(unused)]
+impl<'a> Step<'a> {
+  pub fn new(rule: &'a Rule, rule_index: usize, offset: usize, result: String) -> Self {
+    Self {
+      rule,
+      rule_index,
+      offset,
+      result,
+    }
+  }
+
+  pub fn rule(&self) -> &'a Rule {
+    self.rule
+  }
+
+  /// Index of the rule in the program (starting at 0)
+  pub fn rule_index(&self) -> usize {
+    self.rule_index
+  }
+
+  /// Byte offset in the input string where the rule matched
+  pub fn offset(&self) -> usize {
+    self.offset
+  }
+
+  /// String after the rule was applied
+  pub fn result(&self) -> &str {
+    &self.result
+  }
+
+  pub fn into_result(self) -> String {
+    self.result
+  }
+}
+
+impl LoopInfo {
+  /// Number of executions before the string and state repeat
+  pub fn period(&self) -> usize {
+    self.period
+  }
+
+  /// Index of every rule applied during one pass of the loop, in order
+  pub fn rule_indices(&self) -> &[usize] {
+    &self.rule_indices
+  }
+}
+
+impl fmt::Display for LoadError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      LoadError::Io(e) => write!(f, "{e}"),
+      LoadError::Parse(errors) => {
+        for error in errors {
+          writeln!(f, "{error}\n")?;
+        }
+
+        match errors.len() {
+          1 => write!(f, "found 1 error in the code file"),
+          n => write!(f, "found {n} errors in the code file"),
+        }
+      },
     }
   }
 }
 
+impl Error for LoadError {}
+
 impl ProgramState {
   /// Construct a new program state
   pub fn new() -> Self {
-    Self(HashSet::new())
+    Self(HashMap::new())
   }
-}
 
-impl Program {
-  /// Load and parse a program from a file
-  pub fn from_file<P: AsRef<Path>>(file: P) -> io::Result<Self> {
-    let mut rules = Vec::new();
+  /// Get the number of times a limited rule has been applied
+  pub fn count(&self, rule_index: usize) -> usize {
+    self.0.get(&rule_index).cloned().unwrap_or(0)
+  }
 
-    let file = File::open(file)?;
-    for (line, line_number) in BufReader::new(file).lines().zip(1..) {
-      let line: String = line?;
-
-      let rule = match Self::try_parse_line(&line) {
-        Ok(None) => continue,
-        Ok(Some(rule)) => rule,
-        Err(_) => {
-          return Err(io::Error::new(
-            ErrorKind::InvalidData,
-            format!("invalid rule '{line}' on line {line_number}"),
-          ))
-        },
-      };
+  ///
+  /// Count one more use of a rule with the given limit
+  ///   Returns false without counting if the rule already reached the limit
+  ///
+  pub fn try_count(&mut self, rule_index: usize, limit: Option<usize>) -> bool {
+    let limit = match limit {
+      None => return true,
+      Some(limit) => limit,
+    };
 
-      rules.push(rule);
+    let count = self.0.entry(rule_index).or_insert(0);
+    if *count >= limit {
+      return false;
     }
 
-    Ok(Self { rules })
+    *count += 1;
+    true
   }
+}
 
-  /// Try to parse a single line in the program
-  fn try_parse_line(line: &str) -> Result<Option<Rule>, ()> {
-    // Search for an '=' equal sign
-    let equal_sign = match line.find("=") {
-      None => return Ok(None),
-      Some(index) => index,
-    };
+#[allow(unused)]
+impl Program {
+  /// Construct a new program from a list of rules
+  pub fn new(rules: Vec<Rule>) -> Self {
+    Self { rules }
+  }
 
-    // Make sure there is only one equals sign
-    //  Otherwise, we have a badly-formed substitution
-    if line.rfind("=").unwrap() != equal_sign {
-      return Err(());
-    }
+  /// Load and parse a program from a file
+  pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Self, LoadError> {
+    let code = fs::read_to_string(file).map_err(LoadError::Io)?;
+    Self::parse(&code).map_err(LoadError::Parse)
+  }
 
-    // Check for the prescence of the once ':' operator
-    //  Make sure there is only one operation right before '=' sign
-    let is_once = match line.find(":") {
-      None => false,
-      Some(index) if line.rfind(":").unwrap() != index => return Err(()),
-      Some(index) if index == equal_sign - 1 => true,
-      _ => return Err(()),
-    };
+  ///
+  /// Parse a program from the source code
+  ///   Returns every error found in the code, not just the first one
+  ///
+  pub fn parse(code: &str) -> Result<Self, Vec<ParseError>> {
+    let mut rules = Vec::new();
+    let mut errors = Vec::new();
+
+    for (line, line_number) in code.lines().zip(1..) {
+      match parser::parse_line(line, line_number) {
+        Ok(None) => {},
+        Ok(Some(rule)) => rules.push(rule),
+        Err(e) => errors.push(e),
+      }
+    }
 
-    // Extract the left and right sides of the rule
-    let left_side = if is_once {
-      String::from(&line[0..(equal_sign - 1)])
+    if errors.is_empty() {
+      Ok(Self { rules })
     } else {
-      String::from(&line[0..equal_sign])
-    };
-    let right_side = String::from(&line[(equal_sign + 1)..]);
+      Err(errors)
+    }
+  }
+
+  pub fn rules(&self) -> &[Rule] {
+    &self.rules
+  }
 
-    Ok(Some(Rule::new(left_side, right_side, is_once)))
+  /// Get a rule given the index
+  pub fn rule(&self, rule_index: usize) -> &Rule {
+    &self.rules[rule_index]
   }
 
   /// Print all rules in the program
@@ -127,32 +362,84 @@ impl Program {
     for rule in self.rules.iter() {
       println!("{rule}");
     }
-    println!("");
+    println!();
   }
 
   /// Execute the first matching rule found, returns None if no rules matched
+  ///   This is the simple reference implementation, see `Engine` for the optimized version
   ///   This method requires a program state to be stored between invocations
-  pub fn execute_rule(&self, input: &str, state: &mut ProgramState) -> Option<(String, &Rule)> {
+  ///   The program should stop after applying a rule that halts
+  pub fn execute_rule(&self, input: &str, state: &mut ProgramState) -> Option<Step<'_>> {
     for (rule, rule_index) in self.rules.iter().zip(0..) {
-      // See if input contains the string
-      if !input.contains(rule.left()) {
-        continue;
-      }
+      // See if the rule matches the input string
+      let offset = match rule.find(input) {
+        None => continue,
+        Some(offset) => offset,
+      };
 
-      // Special case for "once" rules
-      if rule.once() {
-        if state.0.contains(&rule_index) {
-          continue;
-        } else {
-          state.0.insert(rule_index);
-        }
+      // Special case for "once" or "repeat" rules
+      if !state.try_count(rule_index, rule.limit()) {
+        continue;
       }
 
       // Apply the rule to the first instance in the string
-      let new_string = input.replacen(rule.left(), rule.right(), 1);
-      return Some((new_string, rule));
+      let new_string = rule.apply(input, offset);
+      return Some(Step {
+        rule,
+        rule_index,
+        offset,
+        result: new_string,
+      });
     }
 
     None
   }
+  ///
+  /// Run the program on the input string until it stops or it exceeds the maximum executions
+  ///   Calls the function after every step of the execution.
+  ///
+  ///   Uses Brent's algorithm to detect infinite loops without storing every string:
+  ///   a saved copy of the string and state is compared with the current one,
+  ///   and the copy gets replaced after a power-of-two number of steps.
+  ///
+  pub fn run(&self, input: &str, max_executions: usize, mut on_step: impl FnMut(&Step)) -> Outcome {
+    let engine = Engine::new(self);
+    let mut cache = MatchCache::default();
+    let mut input = input.to_string();
+    let mut state = ProgramState::new();
+
+    let mut saved = (input.clone(), state.clone());
+    let mut power = 1;
+    let mut loop_rules = Vec::new();
+
+    for _ in 0..max_executions {
+      let step = match engine.execute_rule(&input, &mut state, &mut cache) {
+        None => return Outcome::Finished(input),
+        Some(step) => step,
+      };
+
+      on_step(&step);
+      if step.rule().halts() {
+        return Outcome::Finished(step.into_result());
+      }
+
+      loop_rules.push(step.rule_index());
+      input = step.into_result();
+
+      if input == saved.0 && state == saved.1 {
+        return Outcome::Loop(LoopInfo {
+          period: loop_rules.len(),
+          rule_indices: loop_rules,
+        });
+      }
+
+      if loop_rules.len() == power {
+        saved = (input.clone(), state.clone());
+        power *= 2;
+        loop_rules.clear();
+      }
+    }
+
+    Outcome::Timeout
+  }
 }
@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::install;
+use crate::level::Level;
+use crate::level_pack::{AllLevelPacks, LevelNumber, LevelPack, APP_FOLDER};
+use crate::score::Score;
+
+const PROGRESS_FILE: &str = "progress.json";
+/// Folder in the user data folder with a copy of the best solution for every solved level
+const SOLUTIONS_FOLDER: &str = "solutions";
+
+///
+/// Levels that the player has solved, which is saved between runs
+///   The save file is stored in the user's data folder,
+///   such as `~/.local/share/stringer-puzzles/progress.json` on Linux
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+  /// ID of the level pack that was played last, which is used to resume
+  last_pack: Option<String>,
+  packs: Vec<PackProgress>,
+}
+
+/// Solved levels for a single version of a level pack
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackProgress {
+  id: String,
+  version: String,
+  /// Best solution for every solved level, using the level code as the key
+  levels: BTreeMap<String, LevelProgress>,
+}
+
+/// Best solution for a single level
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LevelProgress {
+  name: String,
+  score: Score,
+  /// Copy of the code file with the best solution, which is kept in the user data folder
+  solution: PathBuf,
+}
+
+#[allow(unused)]
+impl Progress {
+  /// Get the location of the save file, or None if the user does not have a data folder
+  pub fn file() -> Option<PathBuf> {
+    dirs::data_dir().map(|folder| folder.join(APP_FOLDER).join(PROGRESS_FILE))
+  }
+
+  ///
+  /// Load the save file
+  ///   Returns empty progress if the file does not exist yet
+  ///
+  pub fn load() -> io::Result<Self> {
+    let file = Self::file().ok_or_else(no_data_folder)?;
+    match fs::read_to_string(file) {
+      Ok(json) => Ok(serde_json::from_str(&json)?),
+      Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+      Err(e) => Err(e),
+    }
+  }
+
+  ///
+  /// Write the progress to the save file, creating the data folder if needed
+  ///
+  pub fn save(&self) -> io::Result<()> {
+    let file = Self::file().ok_or_else(no_data_folder)?;
+    if let Some(folder) = file.parent() {
+      fs::create_dir_all(folder)?;
+    }
+    fs::write(file, serde_json::to_string_pretty(self)?)
+  }
+
+  /// ID of the level pack that was played last
+  pub fn last_pack(&self) -> Option<&str> {
+    self.last_pack.as_deref()
+  }
+
+  /// Get the solved levels for the current version of the level pack
+  pub fn pack(&self, pack: &LevelPack) -> Option<&PackProgress> {
+    self
+      .packs
+      .iter()
+      .find(|p| p.id == pack.id() && p.version == pack.version())
+  }
+
+  /// Get the file for the copy of the best solution to a level
+  pub fn solution_file(pack: &LevelPack, level_code: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|folder| {
+      folder
+        .join(APP_FOLDER)
+        .join(SOLUTIONS_FOLDER)
+        .join(install::file_name(pack.id()))
+        .join(install::file_name(pack.version()))
+        .join(format!("{}.txt", install::file_name(level_code)))
+    })
+  }
+
+  ///
+  /// Record a solution that passed all of the test cases
+  ///   Only replaces the saved solution if the new one has a better score,
+  ///   in which case the code file is copied to the user data folder so it is kept even if the file changes.
+  ///   Returns true if the solution is the new best for the level
+  ///
+  pub fn record(
+    &mut self,
+    pack: &LevelPack,
+    level_code: &str,
+    level: &Level,
+    score: &Score,
+    solution: &Path,
+  ) -> io::Result<bool> {
+    self.last_pack = Some(pack.id().into());
+
+    let index = match self
+      .packs
+      .iter()
+      .position(|p| p.id == pack.id() && p.version == pack.version())
+    {
+      Some(index) => index,
+      None => {
+        self.packs.push(PackProgress {
+          id: pack.id().into(),
+          version: pack.version().into(),
+          levels: BTreeMap::new(),
+        });
+        self.packs.len() - 1
+      },
+    };
+
+    let levels = &mut self.packs[index].levels;
+    if let Some(best) = levels.get(level_code) {
+      if !score.is_better_than(&best.score, level) {
+        return Ok(false);
+      }
+    }
+
+    let copy = Self::solution_file(pack, level_code).ok_or_else(no_data_folder)?;
+    if let Some(folder) = copy.parent() {
+      fs::create_dir_all(folder)?;
+    }
+    fs::copy(solution, &copy)?;
+
+    levels.insert(
+      level_code.into(),
+      LevelProgress {
+        name: level.name().into(),
+        score: score.clone(),
+        solution: copy,
+      },
+    );
+    Ok(true)
+  }
+
+  ///
+  /// Get the first level in the pack that has not been solved yet
+  ///   Returns None if every level is solved
+  ///
+  pub fn next_unsolved<'a>(&self, pack: &'a LevelPack) -> Option<(LevelNumber, &'a str)> {
+    let solved = self.pack(pack);
+    (1..)
+      .zip(pack.level_codes())
+      .find(|(_, code)| !solved.is_some_and(|solved| solved.levels.contains_key(*code)))
+  }
+
+  ///
+  /// Print the solved levels for every loaded level pack
+  ///
+  pub fn print(&self, all_packs: &AllLevelPacks) {
+    println!("--- Progress: ---");
+    for (pack_code, pack) in all_packs.level_packs() {
+      let solved = self.pack(pack);
+      let num_solved = solved.map_or(0, |solved| solved.levels.len());
+      println!("{} = {} (version {})", pack_code, pack.name(), pack.version());
+      println!("  Solved {} / {} levels", num_solved, pack.levels().len());
+
+      for ((level, level_code), level_number) in pack.levels().iter().zip(pack.level_codes()).zip(1..) {
+        let best = match solved.and_then(|solved| solved.levels.get(level_code)) {
+          Some(best) => best,
+          None => continue,
+        };
+        let (earned, possible) = best.score.stars(level);
+        println!(
+          "  Level {}: {} {} - {} lines, {:.1} average steps",
+          level_number,
+          level.name(),
+          Score::star_symbols(earned, possible),
+          best.score.lines(),
+          best.score.average_steps()
+        );
+        println!("    Best solution saved in {}", best.solution.display());
+      }
+
+      match self.next_unsolved(pack) {
+        Some((level_number, level_code)) => println!("  -> Next Level {} Code: {}\n", level_number, level_code),
+        None => println!("  -> All levels solved!\n"),
+      }
+    }
+  }
+}
+
+#[allow(unused)]
+impl LevelProgress {
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn score(&self) -> &Score {
+    &self.score
+  }
+
+  pub fn solution(&self) -> &Path {
+    &self.solution
+  }
+}
+
+fn no_data_folder() -> io::Error {
+  io::Error::new(ErrorKind::NotFound, "could not find the user data folder")
+}
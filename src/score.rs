@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use crate::level::Level;
+
+/// Symbol for a star that the solution earned
+const STAR: char = '★';
+/// Symbol for a star that the solution missed
+const NO_STAR: char = '☆';
+
+///
+/// How good a solution is, once it passes all of the test cases
+///   Like the A=B game, solving the level earns one star,
+///   and the solution earns one more star for each par target on the level that it meets
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Score {
+  /// Number of rules in the code
+  lines: usize,
+  /// Average number of rules that ran for each test case
+  average_steps: f64,
+  /// Most rules that ran for any single test case
+  max_steps: usize,
+}
+
+#[allow(unused)]
+impl Score {
+  /// Score the code from the number of rules that ran for each test case
+  pub fn new(lines: usize, steps: &[usize]) -> Self {
+    let total: usize = steps.iter().sum();
+    Self {
+      lines,
+      average_steps: total as f64 / steps.len().max(1) as f64,
+      max_steps: steps.iter().copied().max().unwrap_or(0),
+    }
+  }
+
+  pub fn lines(&self) -> usize {
+    self.lines
+  }
+
+  pub fn average_steps(&self) -> f64 {
+    self.average_steps
+  }
+
+  pub fn max_steps(&self) -> usize {
+    self.max_steps
+  }
+
+  /// See if the code has at most `parLines` rules, or `None` if the level has no target
+  pub fn meets_par_lines(&self, level: &Level) -> Option<bool> {
+    level.par_lines().map(|par| self.lines <= par)
+  }
+
+  /// See if the code runs at most `parSteps` rules on average, or `None` if the level has no target
+  pub fn meets_par_steps(&self, level: &Level) -> Option<bool> {
+    level.par_steps().map(|par| self.average_steps <= par as f64)
+  }
+
+  ///
+  /// Get the number of stars the solution earned, along with the most stars possible for the level
+  ///
+  pub fn stars(&self, level: &Level) -> (usize, usize) {
+    let targets = [self.meets_par_lines(level), self.meets_par_steps(level)];
+    let earned = 1 + targets.iter().filter(|met| **met == Some(true)).count();
+    let possible = 1 + targets.iter().filter(|met| met.is_some()).count();
+    (earned, possible)
+  }
+
+  ///
+  /// See if this score is better than another score for the same level
+  ///   More stars are always better, followed by fewer lines and then fewer average steps
+  ///
+  pub fn is_better_than(&self, other: &Score, level: &Level) -> bool {
+    let (stars, other_stars) = (self.stars(level).0, other.stars(level).0);
+    stars > other_stars
+      || (stars == other_stars && (self.lines, self.average_steps) < (other.lines, other.average_steps))
+  }
+
+  /// Get the stars as filled and empty symbols
+  pub fn star_symbols(earned: usize, possible: usize) -> String {
+    (0..possible).map(|i| if i < earned { STAR } else { NO_STAR }).collect()
+  }
+
+  ///
+  /// Print the score and the par targets for the level
+  ///
+  pub fn print(&self, level: &Level) {
+    let par = |par: Option<usize>| par.map(|par| format!(" (par {par})")).unwrap_or_default();
+
+    println!("----- Score: -----");
+    println!("  Lines: {}{}", self.lines, par(level.par_lines()));
+    println!(
+      "  Steps: {:.1} average, {} max{}",
+      self.average_steps,
+      self.max_steps,
+      par(level.par_steps())
+    );
+
+    let (earned, possible) = self.stars(level);
+    println!(
+      "  Stars: {} ({earned} / {possible})\n",
+      Self::star_symbols(earned, possible)
+    );
+  }
+}
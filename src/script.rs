@@ -0,0 +1,346 @@
+use rlua::prelude::*;
+use rlua::StdLib;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use crate::level::EdgeCase;
+use crate::pack_source::PackSource;
+
+/// Only the standard libraries that cannot touch the file system or the rest of the computer
+const SAFE_LIBRARIES: StdLib = StdLib::BASE
+  .union(StdLib::COROUTINE)
+  .union(StdLib::TABLE)
+  .union(StdLib::STRING)
+  .union(StdLib::UTF8)
+  .union(StdLib::MATH);
+
+/// Base library functions that can run code from other files
+const UNSAFE_GLOBALS: &[&str] = &["dofile", "loadfile", "load"];
+
+/// Maximum number of Lua instructions for loading the script or for a single function call
+const MAX_INSTRUCTIONS: u64 = 50_000_000;
+/// How often to check the instruction count
+const INSTRUCTION_CHECK_INTERVAL: u32 = 10_000;
+/// Maximum time for loading the script or for a single function call
+const MAX_CALL_SECONDS: u64 = 10;
+/// Maximum memory the Lua state can use
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Registry key for the modules that have already been loaded by `require`
+const LOADED_MODULES_KEY: &str = "loadedModules";
+
+/// Function that runs on the script's thread with the Lua state
+type Call = Box<dyn FnOnce(&Lua) + Send>;
+
+///
+/// Lua script for a level that stays loaded between calls
+///   The script must define a global `generateTestCase()` function that returns an input and output pair.
+///   It can also define `solve(input)` to get the expected output for any input string,
+///   which should raise an error if the input is not valid for the level,
+///   and `check(input, output)` for levels that accept more than one correct output.
+///   Hand-written test cases can also come from an `edgeCases()` function.
+///
+///   Scripts come from level packs that may be downloaded from anywhere, so they run in a sandbox.
+///   Only the safe standard libraries are available, `require` can only load files from the level pack,
+///   and every call has an instruction budget and a time limit along with an overall memory limit.
+///
+///   The Lua state lives on its own thread, so a call can be abandoned when it runs past the time limit,
+///   even if it is stuck inside a library function like `string.find` that never reaches the instruction check
+///
+pub struct LevelScript {
+  /// Sends calls to the thread that owns the Lua state
+  calls: mpsc::Sender<Call>,
+  instructions: Arc<AtomicU64>,
+  /// Set once a call runs past the time limit, which stops the script for good
+  stopped: Arc<AtomicBool>,
+}
+
+#[allow(unused)]
+impl LevelScript {
+  ///
+  /// Load the Lua code file for a level from the level pack
+  ///   The random number generator is seeded before the script runs
+  ///
+  pub fn load(lua_file: &str, pack_source: &PackSource, seed: u32) -> Result<Self, Box<dyn Error>> {
+    // Try to load the Lua code file into memory
+    let pack_source = pack_source.clone();
+    let lua_code = pack_source.read(lua_file)?;
+
+    let (calls, receiver) = mpsc::channel::<Call>();
+    let script = Self {
+      calls,
+      instructions: Arc::new(AtomicU64::new(0)),
+      stopped: Arc::new(AtomicBool::new(false)),
+    };
+
+    // Run every call on the script's thread until the script is dropped
+    let (instructions, stopped) = (script.instructions.clone(), script.stopped.clone());
+    thread::Builder::new().name(format!("lua {lua_file}")).spawn(move || {
+      let lua = Lua::new_with(SAFE_LIBRARIES);
+      lua.set_memory_limit(Some(MAX_MEMORY_BYTES));
+      set_limit_hook(&lua, instructions, stopped);
+      for call in receiver {
+        call(&lua);
+      }
+    })?;
+
+    let lua_file = lua_file.to_string();
+    script.call(move |lua| {
+      lua.context(|ctx| {
+        let globals = ctx.globals();
+        for name in UNSAFE_GLOBALS {
+          globals.set(*name, LuaValue::Nil)?;
+        }
+
+        // Only allow loading other files from the level pack
+        ctx.set_named_registry_value(LOADED_MODULES_KEY, ctx.create_table()?)?;
+        let require = ctx.create_function(move |ctx, name: String| require_module(ctx, &pack_source, &name))?;
+        globals.set("require", require)?;
+
+        // Seed the random number generator
+        globals
+          .get::<_, LuaTable>("math")?
+          .get::<_, LuaFunction>("randomseed")?
+          .call::<_, ()>(seed)?;
+
+        // Load the script code
+        //  This should define a global function named "generateTestCase"
+        ctx.load(&lua_code).set_name(&format!("@{lua_file}"))?.exec()
+      })
+    })?;
+
+    Ok(script)
+  }
+
+  ///
+  /// Run a function with the Lua state on the script's thread and wait for the result
+  ///   Resets the instruction budget first, and fails if the call runs for more than the time limit.
+  ///   A call that runs too long keeps going in the background until it reaches the next instruction check,
+  ///   but its result is thrown away and the script cannot be used again
+  ///
+  fn call<T: Send + 'static>(
+    &self,
+    function: impl FnOnce(&Lua) -> LuaResult<T> + Send + 'static,
+  ) -> Result<T, Box<dyn Error>> {
+    if self.stopped.load(Ordering::Relaxed) {
+      return Err(time_limit_error());
+    }
+
+    self.instructions.store(0, Ordering::Relaxed);
+    let (sender, result) = mpsc::channel();
+    self
+      .calls
+      .send(Box::new(move |lua| {
+        let _ = sender.send(function(lua));
+      }))
+      .map_err(|_| "script stopped unexpectedly")?;
+
+    match result.recv_timeout(Duration::from_secs(MAX_CALL_SECONDS)) {
+      Ok(result) => result.map_err(script_error),
+      Err(mpsc::RecvTimeoutError::Timeout) => {
+        self.stopped.store(true, Ordering::Relaxed);
+        Err(time_limit_error())
+      },
+      Err(mpsc::RecvTimeoutError::Disconnected) => Err("script stopped unexpectedly".into()),
+    }
+  }
+
+  ///
+  /// Generate the next test cases one-by-one
+  ///
+  pub fn generate_test_cases(&self, n: usize) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    (0..n)
+      .map(|_| {
+        self.call(|lua| {
+          lua.context(|ctx| {
+            let generate_test_case: LuaFunction = ctx.globals().get("generateTestCase")?;
+            generate_test_case.call(())
+          })
+        })
+      })
+      .collect()
+  }
+
+  ///
+  /// Generate a single test case, making sure the generator returns two strings
+  ///   Unlike `generate_test_cases`, numbers are not turned into strings, which helps find mistakes in the generator
+  ///
+  pub fn generate_strict_test_case(&self) -> Result<(String, String), Box<dyn Error>> {
+    let test_case = self.call(|lua| {
+      lua.context::<_, LuaResult<Result<(String, String), String>>>(|ctx| {
+        let generate_test_case: LuaFunction = ctx.globals().get("generateTestCase")?;
+        let (input, output): (LuaValue, LuaValue) = generate_test_case.call(())?;
+        Ok(match (input, output) {
+          (LuaValue::String(input), LuaValue::String(output)) => Ok((input.to_str()?.into(), output.to_str()?.into())),
+          (input, output) => Err(format!(
+            "generateTestCase() must return two strings, but it returned {} and {}",
+            input.type_name(),
+            output.type_name()
+          )),
+        })
+      })
+    });
+
+    Ok(test_case??)
+  }
+
+  /// See if the script defines a global function with the name
+  fn has_function(&self, name: &str) -> bool {
+    let name = name.to_string();
+    self
+      .call(move |lua| {
+        Ok(lua.context(|ctx| matches!(ctx.globals().get::<_, LuaValue>(name), Ok(LuaValue::Function(_)))))
+      })
+      .unwrap_or(false)
+  }
+
+  ///
+  /// Get the hand-written test cases from the `edgeCases()` function, if the script defines one
+  ///   The function returns a list of tables with an `input` and an optional `output`
+  ///
+  pub fn edge_cases(&self) -> Result<Vec<EdgeCase>, Box<dyn Error>> {
+    if !self.has_function("edgeCases") {
+      return Ok(Vec::new());
+    }
+
+    self.call(|lua| {
+      lua.context(|ctx| {
+        let edge_cases: LuaTable = ctx.globals().get::<_, LuaFunction>("edgeCases")?.call(())?;
+        edge_cases
+          .sequence_values::<LuaTable>()
+          .map(|edge_case| {
+            let edge_case = edge_case?;
+            Ok(EdgeCase::new(
+              edge_case.get::<_, String>("input")?,
+              edge_case.get("output")?,
+            ))
+          })
+          .collect()
+      })
+    })
+  }
+
+  /// See if the script defines a `solve(input)` function
+  pub fn has_solve(&self) -> bool {
+    self.has_function("solve")
+  }
+
+  ///
+  /// Get the expected output for any input string
+  ///   Fails if the script does not define `solve(input)` or the input is not valid for the level
+  ///
+  pub fn solve(&self, input: &str) -> Result<String, Box<dyn Error>> {
+    if !self.has_solve() {
+      return Err("the level does not define a solve(input) function".into());
+    }
+
+    // Errors raised by the script explain why the input is not valid
+    let input = input.to_string();
+    self.call(move |lua| {
+      lua.context(|ctx| {
+        let solve: LuaFunction = ctx.globals().get("solve")?;
+        solve.call(input)
+      })
+    })
+  }
+
+  /// See if the script defines a `check(input, output)` function
+  pub fn has_check(&self) -> bool {
+    self.has_function("check")
+  }
+
+  ///
+  /// Ask the level if the output is correct for the input
+  ///   Returns the result of `check(input, output)`, which is either true,
+  ///   or false along with an optional message that explains what is wrong
+  ///
+  pub fn check(&self, input: &str, output: &str) -> Result<(bool, Option<String>), Box<dyn Error>> {
+    let (input, output) = (input.to_string(), output.to_string());
+    self.call(move |lua| {
+      lua.context(|ctx| {
+        let check: LuaFunction = ctx.globals().get("check")?;
+        check.call((input, output))
+      })
+    })
+  }
+}
+
+///
+/// Stop the script once it uses up the instruction budget, or once a call has run past the time limit
+///
+fn set_limit_hook(lua: &Lua, instructions: Arc<AtomicU64>, stopped: Arc<AtomicBool>) {
+  let triggers = LuaHookTriggers {
+    every_nth_instruction: Some(INSTRUCTION_CHECK_INTERVAL),
+    ..Default::default()
+  };
+  lua.set_hook(triggers, move |_, _| {
+    if stopped.load(Ordering::Relaxed) {
+      return Err(LuaError::RuntimeError(time_limit_error().to_string()));
+    }
+
+    let used = instructions.fetch_add(INSTRUCTION_CHECK_INTERVAL.into(), Ordering::Relaxed);
+    if used >= MAX_INSTRUCTIONS {
+      return Err(LuaError::RuntimeError(format!(
+        "script ran for more than {MAX_INSTRUCTIONS} instructions"
+      )));
+    }
+    Ok(())
+  });
+}
+
+///
+/// Load a Lua module from the level pack, like the standard `require` function
+///   Module names use dots to separate folders, so "utils.strings" loads "utils/strings.lua".
+///   Each module only runs once, and later calls return the same value
+///
+fn require_module<'lua>(ctx: LuaContext<'lua>, pack_source: &PackSource, name: &str) -> LuaResult<LuaValue<'lua>> {
+  let loaded: LuaTable = ctx.named_registry_value(LOADED_MODULES_KEY)?;
+  if let Some(module) = loaded.get::<_, Option<LuaValue>>(name)? {
+    return Ok(module);
+  }
+
+  // Module names can only contain simple folder and file names, so they cannot leave the pack folder
+  let valid_part =
+    |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+  if !name.split('.').all(valid_part) {
+    return Err(LuaError::RuntimeError(format!("invalid module name '{name}'")));
+  }
+
+  let file = name.replace('.', "/") + ".lua";
+  let code = pack_source
+    .read(&file)
+    .map_err(|_| LuaError::RuntimeError(format!("module '{name}' not found in the level pack")))?;
+
+  // Modules that do not return anything are stored as true, the same as the standard `require`
+  let module = match ctx
+    .load(&code)
+    .set_name(&format!("@{file}"))?
+    .call::<_, LuaValue>(name)?
+  {
+    LuaValue::Nil => LuaValue::Boolean(true),
+    module => module,
+  };
+  loaded.set(name, module.clone())?;
+  Ok(module)
+}
+
+/// Errors raised by the script are meant for the player, so skip the stack trace
+fn script_error(error: LuaError) -> Box<dyn Error> {
+  match error {
+    LuaError::RuntimeError(message) => message.split("\nstack traceback:").next().unwrap_or_default().into(),
+    LuaError::CallbackError { cause, .. } => script_error(cause.as_ref().clone()),
+    LuaError::MemoryError(_) => format!(
+      "script used more than the memory limit of {} MB",
+      MAX_MEMORY_BYTES / 1024 / 1024
+    )
+    .into(),
+    error => error.into(),
+  }
+}
+
+fn time_limit_error() -> Box<dyn Error> {
+  format!("script ran for more than {MAX_CALL_SECONDS} seconds").into()
+}
@@ -0,0 +1,143 @@
+use rayon::prelude::*;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use crate::level::Level;
+use crate::pack_source::PackSource;
+use crate::script::LevelScript;
+use crate::trace::Verdict;
+
+/// Number of different seeds to ask the generator for more test cases
+const GENERATOR_SEEDS: u32 = 20;
+/// Number of test cases to generate with each seed
+const CASES_PER_SEED: usize = 100;
+/// Maximum number of inputs from `solve(input)` to run the program on
+const MAX_SOLVED_CANDIDATES: usize = 1000;
+/// Stop looking for smaller inputs after this long, since every candidate can run until the step limit
+const MAX_SEARCH_TIME: Duration = Duration::from_secs(10);
+
+///
+/// Search for a smaller input that the program still gets wrong in the same way
+///   First runs the generator with different seeds and keeps any test cases with shorter inputs,
+///   since generators cannot be asked for smaller test cases directly.
+///   If the level defines `solve(input)`, it then keeps removing and replacing characters
+///   as long as the program still disagrees with the expected output.
+///   The `run` function runs the program on an input and expected output and returns the verdict,
+///   which must match the original `verdict` so a wrong output does not turn into a timeout or a loop.
+///   The search stops after a time limit, keeping the smallest failing input found so far.
+///   Returns None if no smaller failing input was found
+///
+pub fn shrink(
+  level: &Level,
+  pack_source: &PackSource,
+  input: &str,
+  expected: &str,
+  verdict: Verdict,
+  run: impl Fn(&str, &str) -> Verdict + Sync,
+) -> Result<Option<(String, String)>, Box<dyn Error>> {
+  let mut best = (input.to_string(), expected.to_string());
+  let fails = |input: &str, output: &str| run(input, output) == verdict;
+  let deadline = Instant::now() + MAX_SEARCH_TIME;
+
+  // Try the smallest generated inputs first, stopping at the first batch with a failure
+  let mut candidates = Vec::new();
+  for seed in 1..=GENERATOR_SEEDS {
+    let script = LevelScript::load(level.lua_file(), pack_source, seed)?;
+    candidates.extend(
+      script
+        .generate_test_cases(CASES_PER_SEED)?
+        .into_iter()
+        .filter(|(candidate, _)| is_smaller(candidate, &best.0)),
+    );
+  }
+  candidates.sort_by(|(a, _), (b, _)| size(a).cmp(&size(b)));
+  candidates.dedup();
+
+  let batch_size = rayon::current_num_threads().max(1);
+  for batch in candidates.chunks(batch_size) {
+    if Instant::now() >= deadline {
+      break;
+    }
+    if let Some(found) = batch.par_iter().find_first(|(input, output)| fails(input, output)) {
+      best = found.clone();
+      break;
+    }
+  }
+
+  // Use the oracle to make the input even smaller, one edit at a time
+  let script = LevelScript::load(level.lua_file(), pack_source, 0)?;
+  if script.has_solve() {
+    let mut alphabet: Vec<char> = best.0.chars().collect();
+    alphabet.sort_unstable();
+    alphabet.dedup();
+
+    let mut tried = 0;
+    'search: while tried < MAX_SOLVED_CANDIDATES {
+      for candidate in reductions(&best.0, &alphabet) {
+        if Instant::now() >= deadline {
+          break 'search;
+        }
+
+        // Skip any inputs that are not valid for the level
+        let output = match script.solve(&candidate) {
+          Ok(output) => output,
+          Err(_) => continue,
+        };
+
+        tried += 1;
+        if fails(&candidate, &output) {
+          best = (candidate, output);
+          continue 'search;
+        }
+        if tried >= MAX_SOLVED_CANDIDATES {
+          break;
+        }
+      }
+      break;
+    }
+  }
+
+  if best.0 == input {
+    Ok(None)
+  } else {
+    Ok(Some(best))
+  }
+}
+
+/// Inputs are ordered by length first, then alphabetically
+fn size(input: &str) -> (usize, &str) {
+  (input.chars().count(), input)
+}
+
+fn is_smaller(a: &str, b: &str) -> bool {
+  size(a) < size(b)
+}
+
+///
+/// Get every input that is one edit smaller than the given input
+///   Removes large chunks first, then single characters,
+///   then replaces characters with ones earlier in the alphabet
+///
+fn reductions(input: &str, alphabet: &[char]) -> Vec<String> {
+  let chars: Vec<char> = input.chars().collect();
+  let mut candidates = Vec::new();
+
+  let mut chunk_size = chars.len();
+  while chunk_size > 0 {
+    for start in (0..chars.len()).step_by(chunk_size) {
+      let end = (start + chunk_size).min(chars.len());
+      candidates.push(chars[..start].iter().chain(&chars[end..]).collect());
+    }
+    chunk_size /= 2;
+  }
+
+  for (index, current) in chars.iter().enumerate() {
+    for replacement in alphabet.iter().filter(|c| *c < current) {
+      let mut replaced = chars.clone();
+      replaced[index] = *replacement;
+      candidates.push(replaced.into_iter().collect());
+    }
+  }
+
+  candidates
+}
@@ -0,0 +1,243 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::program::{Outcome, Step};
+
+/// File format for the execution trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+  /// Single JSON array with one object per test case
+  Json,
+  /// Newline-delimited JSON with one event per line
+  Ndjson,
+}
+
+/// Writes machine-readable execution traces for every test case that runs
+#[derive(Debug)]
+pub struct TraceWriter {
+  format: TraceFormat,
+  writer: BufWriter<File>,
+  cases_written: usize,
+}
+
+///
+/// Trace for a single test case that is stored in a temporary file
+///   Test cases run in parallel, so each one is recorded separately
+///   and then copied to the trace file in order.
+///   A test case can run millions of steps, so the trace is not kept in memory
+///
+#[derive(Debug)]
+pub struct CaseTrace {
+  format: TraceFormat,
+  /// Temporary file, or the first error from creating or writing to it
+  file: io::Result<BufWriter<File>>,
+  steps_written: usize,
+}
+
+/// Start of a test case
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CaseStart<'a> {
+  test_case: usize,
+  input: &'a str,
+  expected: &'a str,
+}
+
+/// Single rule that was applied during a test case
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StepTrace<'a> {
+  rule_index: usize,
+  line: usize,
+  offset: usize,
+  result: &'a str,
+}
+
+/// Final result of a test case
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CaseEnd<'a> {
+  output: Option<&'a str>,
+  verdict: Verdict,
+}
+
+/// Line in the newline-delimited JSON file
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+enum Event<'a> {
+  Case(CaseStart<'a>),
+  Step(StepTrace<'a>),
+  Verdict(CaseEnd<'a>),
+}
+
+/// Final result of a test case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Verdict {
+  Passed,
+  Failed,
+  Timeout,
+  Loop,
+}
+
+impl FromStr for TraceFormat {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "json" => Ok(TraceFormat::Json),
+      "ndjson" => Ok(TraceFormat::Ndjson),
+      _ => Err(format!("unknown trace format '{s}', expected 'json' or 'ndjson'")),
+    }
+  }
+}
+
+impl Verdict {
+  /// Get the verdict for the outcome of running a test case
+  pub fn from_outcome(outcome: &Outcome, expected: &str) -> Self {
+    match outcome {
+      Outcome::Finished(output) if output == expected => Verdict::Passed,
+      Outcome::Finished(_) => Verdict::Failed,
+      Outcome::Timeout => Verdict::Timeout,
+      Outcome::Loop(_) => Verdict::Loop,
+    }
+  }
+
+  /// Short name to show in the summary table
+  pub fn name(&self) -> &'static str {
+    match self {
+      Verdict::Passed => "Passed",
+      Verdict::Failed => "Failed",
+      Verdict::Timeout => "Timeout",
+      Verdict::Loop => "Loop",
+    }
+  }
+}
+
+impl TraceWriter {
+  /// Create the trace file, overwriting any existing file
+  pub fn create<P: AsRef<Path>>(file: P, format: TraceFormat) -> io::Result<Self> {
+    Ok(Self {
+      format,
+      writer: BufWriter::new(File::create(file)?),
+      cases_written: 0,
+    })
+  }
+
+  /// Start the trace for a new test case, which can be recorded on any thread
+  pub fn begin_case(&self, test_case: usize, input: &str, expected: &str) -> CaseTrace {
+    CaseTrace::new(self.format, test_case, input, expected)
+  }
+
+  /// Write the trace for a single test case to the file
+  pub fn write_case(&mut self, case: CaseTrace) -> io::Result<()> {
+    if self.format == TraceFormat::Json {
+      let separator = if self.cases_written == 0 { "[\n" } else { ",\n" };
+      self.writer.write_all(separator.as_bytes())?;
+    }
+
+    let mut file = case.file?.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(0))?;
+    io::copy(&mut file, &mut self.writer)?;
+    self.cases_written += 1;
+    Ok(())
+  }
+
+  /// Close out the trace file after all test cases are written
+  pub fn finish(mut self) -> io::Result<()> {
+    if self.format == TraceFormat::Json {
+      let end = if self.cases_written == 0 { "[]\n" } else { "\n]\n" };
+      self.writer.write_all(end.as_bytes())?;
+    }
+
+    self.writer.flush()
+  }
+}
+
+impl CaseTrace {
+  fn new(format: TraceFormat, test_case: usize, input: &str, expected: &str) -> Self {
+    let mut me = Self {
+      format,
+      file: tempfile::tempfile().map(BufWriter::new),
+      steps_written: 0,
+    };
+
+    let start = CaseStart {
+      test_case,
+      input,
+      expected,
+    };
+
+    match format {
+      TraceFormat::Json => {
+        // Write all fields except for the closing brace, so the steps can be added
+        let start = serde_json::to_string(&start).expect("Failed to serialize trace");
+        me.write(&start.as_bytes()[..(start.len() - 1)]);
+        me.write(b",\"steps\":[");
+      },
+      TraceFormat::Ndjson => me.write_event(&Event::Case(start)),
+    }
+
+    me
+  }
+
+  /// Record a single rule that was applied
+  pub fn write_step(&mut self, step: &Step) {
+    let step = StepTrace {
+      rule_index: step.rule_index(),
+      line: step.rule().line(),
+      offset: step.offset(),
+      result: step.result(),
+    };
+
+    match self.format {
+      TraceFormat::Json => {
+        if self.steps_written > 0 {
+          self.write(b",");
+        }
+        self.write(&serde_json::to_vec(&step).expect("Failed to serialize trace"));
+      },
+      TraceFormat::Ndjson => self.write_event(&Event::Step(step)),
+    }
+
+    self.steps_written += 1;
+  }
+
+  /// Record the final result of the test case
+  pub fn end_case(&mut self, outcome: &Outcome, verdict: Verdict) {
+    let end = CaseEnd {
+      output: match outcome {
+        Outcome::Finished(output) => Some(output),
+        _ => None,
+      },
+      verdict,
+    };
+
+    match self.format {
+      TraceFormat::Json => {
+        let end = serde_json::to_string(&end).expect("Failed to serialize trace");
+        self.write(b"],");
+        self.write(&end.as_bytes()[1..]);
+      },
+      TraceFormat::Ndjson => self.write_event(&Event::Verdict(end)),
+    }
+  }
+
+  fn write_event(&mut self, event: &Event) {
+    let mut line = serde_json::to_vec(event).expect("Failed to serialize trace");
+    line.push(b'\n');
+    self.write(&line);
+  }
+
+  /// Write to the temporary file, keeping the first error so it can be reported when the case is written
+  fn write(&mut self, bytes: &[u8]) {
+    if let Ok(file) = self.file.as_mut() {
+      if let Err(e) = file.write_all(bytes) {
+        self.file = Err(e);
+      }
+    }
+  }
+}